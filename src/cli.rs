@@ -0,0 +1,374 @@
+//! Command line arguments
+//!
+//! Without a subcommand the tool runs interactively, the subcommands allow
+//! inspecting and editing the config without any prompts.
+use anyhow::{bail, Context};
+use clap::{Parser, Subcommand};
+use indicatif::ProgressBar;
+use strum::VariantArray;
+
+use crate::{
+    config::{
+        validate_profile_name, AgentConfig, Config, Queue, QueueId,
+        DEFAULT_PROFILE,
+    },
+    control_api::PauseRequest,
+    endpoints::Endpoints,
+    global::{self, GAME_AGENTS, GAME_MAPS},
+    pause::{Pause, MAX_PAUSE_MINUTES},
+    valo_types::{find_agent, find_map, GameAgent, GameMap},
+};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Config profile to use, created if it does not exist yet
+    #[arg(short, long, global = true, default_value = DEFAULT_PROFILE, value_parser = parse_profile)]
+    pub profile: String,
+    /// Run without any console interaction, e.g. as a background service.
+    /// Exits on Ctrl-C or SIGTERM.
+    #[arg(long)]
+    pub headless: bool,
+    /// Log to stdout instead of the log folder, implies `--plain`
+    #[arg(long)]
+    pub log_stdout: bool,
+    /// Plain console output with the key menu instead of the
+    /// dashboard
+    #[arg(long)]
+    pub plain: bool,
+    /// Start with instalock paused, resume from the menu or control api
+    #[arg(long)]
+    pub paused: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Wait for agent select and instalock (default)
+    Run,
+    #[command(flatten)]
+    OneOff(OneOffCommand),
+}
+
+// Subcommands that do their thing and exit, see `run`. Not a doc comment,
+// clap would show it as the description of the tool.
+#[derive(Debug, Subcommand)]
+pub enum OneOffCommand {
+    /// Show or edit the config
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Agents known to valorant-api.com
+    #[command(subcommand)]
+    Agents(ListCommand),
+    /// Maps known to valorant-api.com
+    #[command(subcommand)]
+    Maps(ListCommand),
+    /// Cached agents and maps, used when valorant-api.com is unreachable
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Pause instalock in the running instance, needs the control api
+    Pause {
+        /// Resume after this many minutes
+        #[arg(long, conflicts_with = "pregames")]
+        minutes: Option<u64>,
+        /// Resume after skipping this many pregames
+        #[arg(long)]
+        pregames: Option<u32>,
+    },
+    /// Resume instalock in the running instance, needs the control api
+    Resume,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the config of the profile
+    Show,
+    /// Set the agents to lock on all maps without their own agents
+    SetDefault {
+        /// Comma separated, in order of preference
+        #[arg(value_delimiter = ',', required = true)]
+        agents: Vec<String>,
+        #[command(flatten)]
+        opts: AgentOpts,
+    },
+    /// Set the agents to lock on a single map
+    SetMap {
+        map: String,
+        /// Comma separated, in order of preference
+        #[arg(value_delimiter = ',', required = true)]
+        agents: Vec<String>,
+        #[command(flatten)]
+        opts: AgentOpts,
+    },
+    /// Set the initial instalock delay
+    SetDelay { ms: u64 },
+}
+
+#[derive(Debug, clap::Args)]
+pub struct AgentOpts {
+    /// Only change the agents for this queue, e.g. `unrated`
+    #[arg(short, long, value_parser = parse_queue)]
+    queue: Option<QueueId>,
+    /// Lock one of the agents at random
+    #[arg(short, long)]
+    random: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ListCommand {
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommand {
+    /// Fetch the agents and maps and replace the cache
+    Refresh,
+}
+
+fn parse_profile(name: &str) -> Result<String, String> {
+    validate_profile_name(name).map(|()| name.to_string())
+}
+
+fn parse_queue(id: &str) -> Result<QueueId, String> {
+    match Queue::from_id(id) {
+        Some(queue) => Ok(queue.id().to_string()),
+        None => Err(format!(
+            "Unknown queue, expected one of: {}",
+            Queue::VARIANTS
+                .iter()
+                .map(Queue::id)
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Runs a subcommand for the active profile. The config is only read by the
+/// subcommands that need it, the others don't create or migrate it.
+pub async fn run(command: OneOffCommand) -> anyhow::Result<()> {
+    match command {
+        OneOffCommand::Pause { minutes, pregames } => {
+            let request = PauseRequest { minutes, pregames };
+            send_pause(&Config::read()?, "pause", &request).await?;
+        }
+        OneOffCommand::Resume => {
+            let request = PauseRequest::default();
+            send_pause(&Config::read()?, "resume", &request).await?;
+        }
+        OneOffCommand::Cache(CacheCommand::Refresh) => {
+            let endpoints = Endpoints::resolve(&Config::read_endpoints());
+            let (api_version, agents, maps) =
+                global::refresh_cache(&endpoints).await?;
+            println!(
+                "Cached {} agents and {} maps, {api_version}",
+                agents.len(),
+                maps.len()
+            );
+        }
+        OneOffCommand::Agents(ListCommand::List) => {
+            let (agents, _) = roster().await;
+            for agent in agents {
+                println!("{}\t{}", agent.name, agent.uuid);
+            }
+        }
+        OneOffCommand::Maps(ListCommand::List) => {
+            let (_, maps) = roster().await;
+            for map in maps {
+                println!("{}\t{}", map.name, map.map_url.0);
+            }
+        }
+        OneOffCommand::Config(ConfigCommand::Show) => {
+            let cfg = Config::read()?;
+            println!("Profile: {}", crate::config::active_profile());
+            println!("Instalock delay: {}ms", cfg.instalock_wait_ms);
+            println!("Lock strategy: {}", cfg.lock_strategy);
+            println!("Lock timing: {}", cfg.lock_timing);
+            println!("Skip hovered agents: {}", cfg.skip_hovered_agents);
+            println!("{}", cfg.describe_agent_config());
+        }
+        OneOffCommand::Config(ConfigCommand::SetDefault {
+            agents: names,
+            opts,
+        }) => {
+            // fetched first, migrating the config may need them
+            let (agents, _) = roster().await;
+            let mut cfg = Config::read()?;
+            let agent_cfg = agent_config(agents, &names, opts.random)?;
+            cfg.map_agent_config_mut(opts.queue).set_default(agent_cfg);
+            cfg.write()?;
+            println!("{}", cfg.describe_agent_config());
+        }
+        OneOffCommand::Config(ConfigCommand::SetMap {
+            map,
+            agents: names,
+            opts,
+        }) => {
+            let (agents, maps) = roster().await;
+            let mut cfg = Config::read()?;
+            let map = find_map(maps, &map)?.map_url.0.clone();
+            let agent_cfg = agent_config(agents, &names, opts.random)?;
+            cfg.map_agent_config_mut(opts.queue).set_map(map, agent_cfg);
+            cfg.write()?;
+            println!("{}", cfg.describe_agent_config());
+        }
+        OneOffCommand::Config(ConfigCommand::SetDelay { ms }) => {
+            let mut cfg = Config::read()?;
+            cfg.instalock_wait_ms = ms;
+            cfg.write()?;
+            println!("Instalock delay: {ms}ms");
+        }
+    }
+    Ok(())
+}
+
+/// Agents and maps from valorant-api.com, or the cache
+async fn roster() -> (&'static [GameAgent], &'static [GameMap]) {
+    let endpoints = Endpoints::resolve(&Config::read_endpoints());
+    global::init_globals(ProgressBar::hidden(), &endpoints).await;
+    (GAME_AGENTS.get().unwrap(), GAME_MAPS.get().unwrap())
+}
+
+/// Posts to `/v1/{path}` of the instance running with the profile
+async fn send_pause(
+    cfg: &Config,
+    path: &str,
+    request: &PauseRequest,
+) -> anyhow::Result<()> {
+    let Some(api) = &cfg.control_api else {
+        bail!(
+            "The control api is not enabled for profile '{}'",
+            crate::config::active_profile()
+        );
+    };
+    if api.port == 0 {
+        bail!(
+            "The control api port of profile '{}' is 0, set a fixed port to \
+             reach the running instance",
+            crate::config::active_profile()
+        );
+    }
+    if request
+        .minutes
+        .is_some_and(|minutes| minutes > MAX_PAUSE_MINUTES)
+    {
+        bail!("Pause for at most {MAX_PAUSE_MINUTES} minutes");
+    }
+    let res = reqwest::Client::new()
+        .post(format!("http://127.0.0.1:{}/v1/{path}", api.port))
+        .bearer_auth(&api.token)
+        .json(request)
+        .send()
+        .await
+        .context("Failed to reach the running instance")?;
+    if !res.status().is_success() {
+        bail!(
+            "Control api returned {}: {}",
+            res.status(),
+            res.text().await.unwrap_or_default()
+        );
+    }
+    let pause: Pause = res.json().await?;
+    println!("Instalock {pause}");
+    Ok(())
+}
+
+fn agent_config(
+    agents: &[GameAgent],
+    names: &[String],
+    random: bool,
+) -> anyhow::Result<AgentConfig> {
+    let ids = names
+        .iter()
+        .map(|name| Ok(find_agent(agents, name)?.uuid.clone()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(if random {
+        AgentConfig::RandomOf(ids)
+    } else {
+        AgentConfig::Some(ids)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::control_api::ControlApiConfig;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_commands() {
+        let cli = Cli::try_parse_from(["valorant-instalock"]).unwrap();
+        assert!(cli.command.is_none());
+        assert_eq!(cli.profile, DEFAULT_PROFILE);
+        let cli = Cli::try_parse_from([
+            "valorant-instalock",
+            "config",
+            "set-map",
+            "Ascent",
+            "Sova,Jett",
+            "--queue",
+            "unrated",
+            "-p",
+            "ranked",
+        ])
+        .unwrap();
+        assert_eq!(cli.profile, "ranked");
+        let Some(Command::OneOff(OneOffCommand::Config(
+            ConfigCommand::SetMap { map, agents, opts },
+        ))) = cli.command
+        else {
+            panic!("unexpected {:?}", cli.command);
+        };
+        assert_eq!(map, "Ascent");
+        assert_eq!(agents, vec!["Sova", "Jett"]);
+        assert_eq!(opts.queue.as_deref(), Some("unrated"));
+        assert!(!opts.random);
+        assert!(Cli::try_parse_from([
+            "valorant-instalock",
+            "config",
+            "set-default",
+            "Jett",
+            "--queue",
+            "ranked"
+        ])
+        .is_err());
+        let cli = Cli::try_parse_from([
+            "valorant-instalock",
+            "pause",
+            "--pregames",
+            "2",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::OneOff(OneOffCommand::Pause {
+                minutes: None,
+                pregames: Some(2)
+            }))
+        ));
+        assert!(Cli::try_parse_from([
+            "valorant-instalock",
+            "pause",
+            "--pregames",
+            "2",
+            "--minutes",
+            "30",
+        ])
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pause_needs_fixed_port() {
+        let cfg = Config {
+            control_api: Some(ControlApiConfig {
+                port: 0,
+                token: "token".to_string(),
+            }),
+            ..Default::default()
+        };
+        let err = send_pause(&cfg, "pause", &PauseRequest::default())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("port"), "{err}");
+    }
+}
@@ -1,18 +1,77 @@
-use std::{collections::HashMap, fmt::Display};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
 use serde::{Deserialize, Serialize};
 use strum::VariantArray;
+use tokio::{
+    sync::{mpsc::channel, watch},
+    time::Instant,
+};
 
 use crate::{
-    global::GAME_AGENTS,
+    control_api::ControlApiConfig,
+    endpoints::EndpointOverrides,
+    global::{GAME_AGENTS, GAME_MAPS},
+    keybindings::Keybindings,
     valo_types::{GameAgent, GameMap},
-    DIALOG_THEME, DONT_SAVE_CONFIG,
+    valorant_client::retry::RetryPolicies,
+    webhooks::WebhookConfig,
+    ACTIVE_PROFILE, CONFIG, CONFIG_FILES, DIALOG_THEME, DONT_SAVE_CONFIG,
 };
 
+mod migration;
+pub mod validation;
+
+/// Profile stored in `config_v1.json`, used when no profile is chosen
+pub const DEFAULT_PROFILE: &str = "default";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub instalock_wait_ms: u64,
     pub map_agent_config: MapAgentConfig,
+    /// Overrides for the remote and local endpoints, see [`crate::endpoints`]
+    #[serde(default, skip_serializing_if = "EndpointOverrides::is_empty")]
+    pub endpoints: EndpointOverrides,
+    /// Also skip agents a teammate is hovering, not only locked ones
+    #[serde(default)]
+    pub skip_hovered_agents: bool,
+    #[serde(default)]
+    pub lock_strategy: LockStrategy,
+    #[serde(default)]
+    pub lock_timing: LockTiming,
+    /// Locks an agent this long before the agent select timer runs out if
+    /// none is locked by then, also with [`LockStrategy::HoverOnly`]. Only
+    /// if agents are configured for the map, disabled if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety_lock_ms: Option<u64>,
+    /// Overrides `map_agent_config` for the queue with the given id
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub queue_agent_config: HashMap<QueueId, MapAgentConfig>,
+    /// Local HTTP api to control the running instance, disabled if not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub control_api: Option<ControlApiConfig>,
+    /// Notified on instalocks and match start/end
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Key chords in the console window, see [`crate::keybindings`]
+    #[serde(default, skip_serializing_if = "Keybindings::is_default")]
+    pub keybindings: Keybindings,
+    /// How failed requests are retried, see [`crate::valorant_client::retry`]
+    #[serde(default, skip_serializing_if = "RetryPolicies::is_default")]
+    pub retry: RetryPolicies,
+    /// Interval of the requests that keep the connection to the glz servers
+    /// open while in the menus, 0 to disable
+    #[serde(default = "default_glz_keep_warm_ms")]
+    pub glz_keep_warm_ms: u64,
+}
+
+fn default_glz_keep_warm_ms() -> u64 {
+    20_000
 }
 
 impl Default for Config {
@@ -20,6 +79,96 @@ impl Default for Config {
         Self {
             instalock_wait_ms: 500,
             map_agent_config: MapAgentConfig::None,
+            endpoints: EndpointOverrides::default(),
+            skip_hovered_agents: false,
+            lock_strategy: LockStrategy::default(),
+            lock_timing: LockTiming::default(),
+            safety_lock_ms: None,
+            queue_agent_config: HashMap::new(),
+            control_api: None,
+            webhooks: Vec::new(),
+            keybindings: Keybindings::default(),
+            retry: RetryPolicies::default(),
+            glz_keep_warm_ms: default_glz_keep_warm_ms(),
+        }
+    }
+}
+
+/// How the chosen agent is picked in agent select
+#[derive(
+    Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum LockStrategy {
+    #[default]
+    LockImmediately,
+    /// Only hover (select) the agent, the user locks it manually
+    HoverOnly,
+    /// Hover the agent first to show intent, then lock it after `delay_ms`
+    HoverThenLock { delay_ms: u64 },
+}
+
+impl Display for LockStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockStrategy::LockImmediately => write!(f, "Lock immediately"),
+            LockStrategy::HoverOnly => write!(f, "Hover only"),
+            LockStrategy::HoverThenLock { delay_ms } => {
+                write!(f, "Hover, then lock after {delay_ms}ms")
+            }
+        }
+    }
+}
+
+/// When the first agent is picked in agent select, relative to the start or
+/// the end of the phase. The phase timer is taken from the pregame match.
+#[derive(
+    Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum LockTiming {
+    /// `instalock_wait_ms` after the pregame started
+    #[default]
+    AfterStart,
+    /// With `ms` left on the timer
+    BeforeEnd { ms: u64 },
+    /// Once all teammates locked their agents, at the latest with
+    /// `min_left_ms` left on the timer
+    AsLateAsPossible { min_left_ms: u64 },
+}
+
+impl LockTiming {
+    /// When to pick the first agent at the latest. Falls back to
+    /// `wait_ms` after `begin` if the phase end is unknown.
+    pub fn lock_at(
+        self,
+        wait_ms: u64,
+        begin: Instant,
+        phase_end: Option<Instant>,
+    ) -> Instant {
+        let left_ms = match self {
+            LockTiming::AfterStart => None,
+            LockTiming::BeforeEnd { ms } => Some(ms),
+            LockTiming::AsLateAsPossible { min_left_ms } => Some(min_left_ms),
+        };
+        match left_ms.zip(phase_end) {
+            Some((ms, end)) => end
+                .checked_sub(Duration::from_millis(ms))
+                .map_or(begin, |at| at.max(begin)),
+            None => begin + Duration::from_millis(wait_ms),
+        }
+    }
+}
+
+impl Display for LockTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockTiming::AfterStart => write!(f, "After the initial delay"),
+            LockTiming::BeforeEnd { ms } => {
+                write!(f, "With {ms}ms left on the timer")
+            }
+            LockTiming::AsLateAsPossible { min_left_ms } => write!(
+                f,
+                "After all teammates, at least {min_left_ms}ms before the end"
+            ),
         }
     }
 }
@@ -46,29 +195,146 @@ impl Display for PromptRandomInstalock {
 }
 
 impl Config {
+    /// Reads the active profile
     pub fn read() -> anyhow::Result<Self> {
-        if std::fs::try_exists(&crate::CONFIG_FILES.config)
-            .is_ok_and(|exists| !exists)
-        {
+        Self::read_profile(&active_profile())
+    }
+
+    pub fn read_profile(profile: &str) -> anyhow::Result<Self> {
+        let path = Self::profile_path(profile);
+        if std::fs::exists(&path).is_ok_and(|exists| !exists) {
             eprintln!("Config file does not exist, creating default config");
-            log::warn!("Config file does not exist, creating default config");
+            log::warn!(
+                "Config file for profile {profile} does not exist, creating default config"
+            );
             let cfg = Self::default();
-            cfg.write()?;
+            cfg.write_profile(profile)?;
             return Ok(cfg);
         }
-        Ok(serde_json::from_slice(&std::fs::read(
-            &crate::CONFIG_FILES.config,
-        )?)?)
+        migration::read(&path)
     }
 
+    /// Endpoint overrides of the active profile, read without migrating or
+    /// creating the file as the migration may need the endpoints
+    pub fn read_endpoints() -> EndpointOverrides {
+        let path = Self::profile_path(&active_profile());
+        if std::fs::exists(&path).is_ok_and(|exists| !exists) {
+            return EndpointOverrides::default();
+        }
+        migration::read_endpoints(&path).unwrap_or_else(|err| {
+            log::warn!("Failed to read endpoints from {path:?}: {err:#}");
+            EndpointOverrides::default()
+        })
+    }
+
+    /// Writes to the active profile
     pub fn write(&self) -> anyhow::Result<()> {
+        self.write_profile(&active_profile())
+    }
+
+    pub fn write_profile(&self, profile: &str) -> anyhow::Result<()> {
         if DONT_SAVE_CONFIG.load(std::sync::atomic::Ordering::Relaxed) {
             return Ok(());
         }
-        Ok(std::fs::write(
-            &crate::CONFIG_FILES.config,
-            serde_json::to_vec_pretty(&self)?,
-        )?)
+        migration::write(&Self::profile_path(profile), self)
+    }
+
+    /// Saves to the active profile and swaps the running config, so the
+    /// changes apply to the next pregame
+    pub fn apply(&self) -> anyhow::Result<()> {
+        self.write()?;
+        CONFIG.send_replace(self.clone());
+        Ok(())
+    }
+
+    /// `config_v1.json` for the default profile, `config_v1.{profile}.json`
+    /// for all others. The file names predate [`migration`], the actual
+    /// format version is stored inside the file.
+    pub fn profile_path(profile: &str) -> PathBuf {
+        if profile == DEFAULT_PROFILE {
+            CONFIG_FILES.config.clone()
+        } else {
+            CONFIG_FILES
+                .config
+                .with_file_name(format!("config_v1.{profile}.json"))
+        }
+    }
+
+    /// All profiles in the config directory, the default profile first
+    pub fn profiles() -> Vec<String> {
+        let dir = CONFIG_FILES.config.parent().unwrap();
+        let mut profiles: Vec<_> = std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| {
+                profile_from_file_name(entry.ok()?.file_name().to_str()?)
+            })
+            .filter(|profile| profile != DEFAULT_PROFILE)
+            .collect();
+        profiles.sort();
+        profiles.insert(0, DEFAULT_PROFILE.to_string());
+        profiles
+    }
+
+    /// Makes `profile` the active config. The running client picks it up for
+    /// the next pregame, no restart needed.
+    pub fn switch_profile(profile: &str) -> anyhow::Result<Self> {
+        let cfg = Self::read_profile(profile)?;
+        *ACTIVE_PROFILE.lock().unwrap() = profile.to_string();
+        DONT_SAVE_CONFIG.store(false, std::sync::atomic::Ordering::Relaxed);
+        CONFIG.send_replace(cfg.clone());
+        log::info!("Switched to profile {profile}");
+        Ok(cfg)
+    }
+
+    /// Prompts for the profile to switch to. A newly created profile starts
+    /// as a copy of the active config if the user wants to.
+    pub fn prompt_profile() -> Option<String> {
+        let active = active_profile();
+        let profiles = Self::profiles();
+        let items = profiles
+            .iter()
+            .map(|profile| {
+                if profile == &active {
+                    format!("{profile} (active)")
+                } else {
+                    profile.clone()
+                }
+            })
+            .chain(Some("New profile".to_string()))
+            .collect::<Vec<_>>();
+        let i = dialoguer::Select::with_theme(&*DIALOG_THEME)
+            .with_prompt("Which profile do you want to use?")
+            .items(&items)
+            .default(profiles.iter().position(|p| p == &active).unwrap_or(0))
+            .interact_opt()
+            .unwrap()?;
+        if let Some(profile) = profiles.get(i) {
+            return Some(profile.clone());
+        }
+        let profile: String = dialoguer::Input::with_theme(&*DIALOG_THEME)
+            .with_prompt("Profile name")
+            .validate_with(|name: &String| {
+                validate_profile_name(name)?;
+                if profiles.contains(name) {
+                    return Err("Profile already exists".to_string());
+                }
+                Ok(())
+            })
+            .interact_text()
+            .unwrap();
+        if dialoguer::Confirm::with_theme(&*DIALOG_THEME)
+            .with_prompt(format!("Copy the settings of profile {active}?"))
+            .default(true)
+            .interact()
+            .unwrap()
+        {
+            if let Err(err) = CONFIG.borrow().write_profile(&profile) {
+                eprintln!("Failed to copy profile: {err}");
+                log::error!("Failed to copy profile {active}: {err}");
+            }
+        }
+        Some(profile)
     }
 
     pub fn prompt_instalock_wait_ms(
@@ -82,6 +348,92 @@ impl Config {
         cfg
     }
 
+    pub fn prompt_lock_strategy(prev: Option<Config>) -> Self {
+        let mut cfg = prev.unwrap_or_default();
+        let (default, prev_delay) = match cfg.lock_strategy {
+            LockStrategy::LockImmediately => (0, 1000),
+            LockStrategy::HoverOnly => (1, 1000),
+            LockStrategy::HoverThenLock { delay_ms } => (2, delay_ms),
+        };
+        let Some(i) = dialoguer::Select::with_theme(&*DIALOG_THEME)
+            .with_prompt("How do you want to pick your Agent?")
+            .items(&["Lock immediately", "Hover only", "Hover, then lock"])
+            .default(default)
+            .interact_opt()
+            .unwrap()
+        else {
+            return cfg;
+        };
+        cfg.lock_strategy = match i {
+            0 => LockStrategy::LockImmediately,
+            1 => LockStrategy::HoverOnly,
+            _ => LockStrategy::HoverThenLock {
+                delay_ms: dialoguer::Input::<u64>::new()
+                    .with_prompt("Lock after hovering for (ms)")
+                    .default(prev_delay)
+                    .interact()
+                    .unwrap(),
+            },
+        };
+        cfg
+    }
+
+    pub fn prompt_lock_timing(prev: Option<Config>) -> Self {
+        let mut cfg = prev.unwrap_or_default();
+        let (default, prev_ms) = match cfg.lock_timing {
+            LockTiming::AfterStart => (0, 5000),
+            LockTiming::BeforeEnd { ms } => (1, ms),
+            LockTiming::AsLateAsPossible { min_left_ms } => (2, min_left_ms),
+        };
+        let Some(i) = dialoguer::Select::with_theme(&*DIALOG_THEME)
+            .with_prompt("When do you want to pick your Agent?")
+            .items(&[
+                "After an initial delay",
+                "With some time left on the timer",
+                "After all teammates, before the timer runs out",
+            ])
+            .default(default)
+            .interact_opt()
+            .unwrap()
+        else {
+            return cfg;
+        };
+        if i == 0 {
+            cfg.lock_timing = LockTiming::AfterStart;
+            return Self::prompt_instalock_wait_ms(Some(cfg));
+        }
+        cfg.lock_timing = match i {
+            1 => LockTiming::BeforeEnd {
+                ms: dialoguer::Input::<u64>::new()
+                    .with_prompt("Time left on the timer (ms)")
+                    .default(prev_ms)
+                    .interact()
+                    .unwrap(),
+            },
+            _ => LockTiming::AsLateAsPossible {
+                min_left_ms: dialoguer::Input::<u64>::new()
+                    .with_prompt("Lock at the latest with time left (ms)")
+                    .default(prev_ms)
+                    .interact()
+                    .unwrap(),
+            },
+        };
+        cfg
+    }
+
+    pub fn prompt_skip_hovered_agents(prev: Option<Config>) -> Self {
+        let mut cfg = prev.unwrap_or_default();
+        cfg.skip_hovered_agents =
+            dialoguer::Confirm::with_theme(&*DIALOG_THEME)
+                .with_prompt(
+                    "Do you want to skip agents your teammates are hovering?",
+                )
+                .default(cfg.skip_hovered_agents)
+                .interact()
+                .unwrap();
+        cfg
+    }
+
     fn prompt_agent_config_for_each_map(
         agents: &Vec<GameAgent>,
         maps: &Vec<GameMap>,
@@ -96,14 +448,14 @@ impl Config {
                 rndm,
                 default,
             ) {
-                map_agent_cfg.insert(map.name.0.clone(), agent_cfg);
+                map_agent_cfg.insert(map.map_url.0.clone(), agent_cfg);
             }
         }
         map_agent_cfg
     }
 
     fn prompt_agent_config(
-        agents: &Vec<GameAgent>,
+        agents: &[GameAgent],
         map: Option<&str>,
         rndm: PromptRandomInstalock,
         default: bool,
@@ -119,13 +471,12 @@ impl Config {
             Ok(agents) if agents.is_empty() => AgentConfig::None,
             Ok(agents) => {
                 if rndm {
-                    let mut agents: Vec<_> =
-                        agents.into_iter().map(|a| a.name.0).collect();
-                    agents.sort();
-                    AgentConfig::RandomOf(agents)
+                    AgentConfig::RandomOf(
+                        agents.into_iter().map(|a| a.uuid).collect(),
+                    )
                 } else {
                     AgentConfig::Some(
-                        agents.into_iter().map(|a| a.name.0).collect(),
+                        agents.into_iter().map(|a| a.uuid).collect(),
                     )
                 }
             }
@@ -192,7 +543,7 @@ impl Config {
     // }
 
     fn select_agents(
-        agents: &Vec<GameAgent>,
+        agents: &[GameAgent],
         map: Option<&str>,
         default: bool,
     ) -> Option<Result<Vec<GameAgent>, ()>> {
@@ -262,18 +613,13 @@ impl Config {
         ))
     }
 
-    fn select_maps(maps: &Vec<GameMap>) -> Option<Vec<MapName>> {
+    fn select_maps(maps: &Vec<GameMap>) -> Option<Vec<MapId>> {
         dialoguer::MultiSelect::with_theme(&*DIALOG_THEME)
             .with_prompt("Select Maps")
             .items(maps)
             .interact_opt()
             .unwrap()
-            .map(|v| {
-                let mut maps: Vec<_> =
-                    v.into_iter().map(|i| maps[i].name.0.clone()).collect();
-                maps.sort();
-                maps
-            })
+            .map(|v| v.into_iter().map(|i| maps[i].map_url.0.clone()).collect())
     }
 
     pub fn prompt_map_agent_cfg(
@@ -282,8 +628,70 @@ impl Config {
         agents: &Vec<GameAgent>,
     ) -> Option<Self> {
         let mut cfg = prev.unwrap_or_default();
-        cfg.map_agent_config = match Self::prompt_map_agent_cfg_kind() {
-            None => cfg.map_agent_config,
+        match Self::select_queue(&cfg)? {
+            None => {
+                cfg.map_agent_config = Self::prompt_map_agent_cfg_for_queue(
+                    cfg.map_agent_config,
+                    maps,
+                    agents,
+                )?;
+            }
+            Some(queue) => {
+                if Self::prompt_use_default_for_queue(queue)? {
+                    cfg.queue_agent_config.remove(queue.id());
+                } else {
+                    let prev = cfg
+                        .queue_agent_config
+                        .remove(queue.id())
+                        .unwrap_or_default();
+                    cfg.queue_agent_config.insert(
+                        queue.id().to_string(),
+                        Self::prompt_map_agent_cfg_for_queue(
+                            prev, maps, agents,
+                        )?,
+                    );
+                }
+            }
+        }
+        Some(cfg)
+    }
+
+    fn select_queue(cfg: &Config) -> Option<Option<Queue>> {
+        let items = Some("All Queues (default)".to_string())
+            .into_iter()
+            .chain(Queue::VARIANTS.iter().map(|queue| {
+                if cfg.queue_agent_config.contains_key(queue.id()) {
+                    format!("{queue} (custom)")
+                } else {
+                    format!("{queue}")
+                }
+            }))
+            .collect::<Vec<_>>();
+        dialoguer::Select::with_theme(&*DIALOG_THEME)
+            .with_prompt("Which Queue do you want to configure?")
+            .items(&items)
+            .default(0)
+            .interact_opt()
+            .unwrap()
+            .map(|i| i.checked_sub(1).map(|i| Queue::VARIANTS[i]))
+    }
+
+    fn prompt_use_default_for_queue(queue: Queue) -> Option<bool> {
+        dialoguer::Select::with_theme(&*DIALOG_THEME)
+            .with_prompt(format!("How do you want to configure {queue}?"))
+            .items(&["Custom Agents for this Queue", "Use the default config"])
+            .interact_opt()
+            .unwrap()
+            .map(|i| i == 1)
+    }
+
+    fn prompt_map_agent_cfg_for_queue(
+        prev: MapAgentConfig,
+        maps: &Vec<GameMap>,
+        agents: &Vec<GameAgent>,
+    ) -> Option<MapAgentConfig> {
+        Some(match Self::prompt_map_agent_cfg_kind() {
+            None => prev,
             Some((MapAgentConfigKind::None, _)) => MapAgentConfig::None,
             Some((MapAgentConfigKind::Default, rndm)) => {
                 // prompt default agent only
@@ -319,48 +727,265 @@ impl Config {
                     ),
                 }
             }
-        };
-        Some(cfg)
+        })
     }
 
-    pub fn get_agents(&self, map_name: &str) -> Vec<GameAgent> {
-        match &self.map_agent_config {
-            MapAgentConfig::None => vec![],
-            MapAgentConfig::Default(agents) => agents.get_agents(),
-            MapAgentConfig::PerSelectedMap { map_agents } => map_agents
-                .get(map_name)
-                .map_or(vec![], |cfg| cfg.get_agents()),
-            MapAgentConfig::DefaultOnSelectedMaps { default, maps } => {
-                if maps
-                    .iter()
-                    .map(|a| a.as_str())
-                    .find(|a| a == &map_name)
-                    .is_some()
-                {
-                    default.get_agents()
-                } else {
-                    vec![]
+    /// The agent config for the queue, falls back to `map_agent_config`
+    pub fn map_agent_config_for(&self, queue: Option<&str>) -> &MapAgentConfig {
+        queue
+            .and_then(|queue| self.queue_agent_config.get(queue))
+            .unwrap_or(&self.map_agent_config)
+    }
+
+    /// The agent config to edit for the queue. A queue without its own
+    /// config starts out as a copy of `map_agent_config`.
+    pub fn map_agent_config_mut(
+        &mut self,
+        queue: Option<QueueId>,
+    ) -> &mut MapAgentConfig {
+        match queue {
+            None => &mut self.map_agent_config,
+            Some(queue) => self
+                .queue_agent_config
+                .entry(queue)
+                .or_insert_with(|| self.map_agent_config.clone()),
+        }
+    }
+
+    pub fn get_agents(&self, map: &str, queue: Option<&str>) -> Vec<GameAgent> {
+        self.map_agent_config_for(queue).get_agents(map)
+    }
+
+    /// The default config followed by all queue specific configs
+    pub fn describe_agent_config(&self) -> String {
+        let mut out = format!("{}", self.map_agent_config);
+        let mut queues: Vec<_> = self.queue_agent_config.iter().collect();
+        queues.sort_by_key(|(queue, _)| queue.as_str());
+        for (queue, cfg) in queues {
+            let queue = match Queue::from_id(queue) {
+                Some(known) => known.to_string(),
+                None => queue.clone(),
+            };
+            out.push_str(&format!("\n{queue} => {cfg}"));
+        }
+        out
+    }
+}
+
+/// Name of the profile the active config was loaded from
+pub fn active_profile() -> String {
+    ACTIVE_PROFILE.lock().unwrap().clone()
+}
+
+/// Watches the config directory and reloads the active profile when its file
+/// is edited externally
+pub fn watch_config() -> anyhow::Result<()> {
+    let (watcher_tx, mut watcher_rx) = channel(100);
+    let mut watcher = RecommendedWatcher::new(
+        move |event| {
+            let _ = watcher_tx.blocking_send(event);
+        },
+        notify::Config::default(),
+    )?;
+    watcher.watch(
+        CONFIG_FILES.config.parent().unwrap(),
+        RecursiveMode::NonRecursive,
+    )?;
+    tokio::task::spawn(async move {
+        #[allow(unused)]
+        let watcher = watcher;
+        while let Some(msg) = watcher_rx.recv().await {
+            match msg {
+                Ok(notify::Event {
+                    kind: EventKind::Modify(_) | EventKind::Create(_),
+                    paths,
+                    ..
+                }) => {
+                    let path = Config::profile_path(&active_profile());
+                    if !paths.iter().any(|p| p.as_path().eq(&path)) {
+                        continue;
+                    }
+                    match reload_config(&CONFIG, &path) {
+                        Ok(true) => {
+                            let now = chrono::Local::now();
+                            status_line!(
+                                "{} - Reloaded edited config file",
+                                now.format("%H:%M:%S")
+                            );
+                            log::info!("Reloaded config from {path:?}");
+                            validation::report_active(
+                                GAME_AGENTS.get().map_or(&[], |a| a),
+                                GAME_MAPS.get().map_or(&[], |m| m),
+                            );
+                        }
+                        Ok(false) => (),
+                        // editors might write the file in several steps, the
+                        // next event will retry
+                        Err(err) => log::warn!(
+                            "Failed to reload config from {path:?}: {err}"
+                        ),
+                    }
                 }
+                Ok(_) => (),
+                Err(err) => log::warn!("config watcher error {err}"),
             }
-            MapAgentConfig::PerSelectedMapOrDefault {
-                default,
-                map_agents,
-            } => map_agents
-                .get(map_name)
-                .map_or(default.get_agents(), |cfg| cfg.get_agents()),
         }
+        log::warn!("Config watcher stopped");
+    });
+    Ok(())
+}
+
+/// Replaces the config in `tx` with the one at `path`, returns whether it
+/// actually changed
+fn reload_config(
+    tx: &watch::Sender<Config>,
+    path: &Path,
+) -> anyhow::Result<bool> {
+    let (cfg, _) = migration::parse(&std::fs::read(path)?)?;
+    let new = serde_json::to_value(&cfg)?;
+    let mut restart_required = false;
+    let changed = tx.send_if_modified(|prev| {
+        if serde_json::to_value(&*prev).ok().as_ref() == Some(&new) {
+            return false;
+        }
+        restart_required = prev.endpoints != cfg.endpoints
+            || prev.control_api != cfg.control_api;
+        *prev = cfg;
+        true
+    });
+    if changed {
+        // the file is valid again, stop using the temporary config
+        DONT_SAVE_CONFIG.store(false, std::sync::atomic::Ordering::Relaxed);
+    }
+    if restart_required {
+        status_line!(
+            "{}",
+            console::style(
+                "Endpoint and control api changes will be applied after restarting the application."
+            )
+            .yellow()
+        );
+    }
+    Ok(changed)
+}
+
+/// Profile names end up in file names, so only allow a safe subset
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("Profile name must not be empty".to_string());
+    }
+    if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+    {
+        return Err(
+            "Profile name may only contain letters, digits, spaces, - and _"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+fn profile_from_file_name(file_name: &str) -> Option<String> {
+    if file_name == "config_v1.json" {
+        return Some(DEFAULT_PROFILE.to_string());
+    }
+    let profile = file_name
+        .strip_prefix("config_v1.")?
+        .strip_suffix(".json")?;
+    validate_profile_name(profile).ok()?;
+    Some(profile.to_string())
+}
+
+/// `map_url` of a [`GameMap`], e.g. `/Game/Maps/Ascent/Ascent`
+pub type MapId = String;
+/// `uuid` of a [`GameAgent`]
+pub type AgentId = String;
+/// `QueueID` of the pregame match, see [`Queue`]
+pub type QueueId = String;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, VariantArray)]
+pub enum Queue {
+    Competitive,
+    Unrated,
+    Swiftplay,
+    SpikeRush,
+    Deathmatch,
+    TeamDeathmatch,
+    Escalation,
+    Replication,
+    Premier,
+    Custom,
+}
+
+impl Queue {
+    pub fn id(&self) -> &'static str {
+        match self {
+            Queue::Competitive => "competitive",
+            Queue::Unrated => "unrated",
+            Queue::Swiftplay => "swiftplay",
+            Queue::SpikeRush => "spikerush",
+            Queue::Deathmatch => "deathmatch",
+            Queue::TeamDeathmatch => "hurm",
+            Queue::Escalation => "ggteam",
+            Queue::Replication => "onefa",
+            Queue::Premier => "premier",
+            // not an actual QueueID, see PregameMatch::queue
+            Queue::Custom => "custom",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::VARIANTS
+            .iter()
+            .copied()
+            .find(|queue| queue.id() == id)
     }
 }
 
-pub type MapName = String;
-pub type AgentName = String;
+impl Display for Queue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Queue::Competitive => "Competitive",
+                Queue::Unrated => "Unrated",
+                Queue::Swiftplay => "Swiftplay",
+                Queue::SpikeRush => "Spike Rush",
+                Queue::Deathmatch => "Deathmatch",
+                Queue::TeamDeathmatch => "Team Deathmatch",
+                Queue::Escalation => "Escalation",
+                Queue::Replication => "Replication",
+                Queue::Premier => "Premier",
+                Queue::Custom => "Custom Game",
+            }
+        )
+    }
+}
+
+/// Display name of the agent, the id itself if it is not known
+pub fn agent_name(id: &str) -> String {
+    GAME_AGENTS
+        .get()
+        .and_then(|agents| agents.iter().find(|a| a.uuid == id))
+        .map_or_else(|| id.to_string(), |a| a.name.0.clone())
+}
+
+/// Display name of the map, the id itself if it is not known
+pub fn map_name(id: &str) -> String {
+    GAME_MAPS
+        .get()
+        .and_then(|maps| maps.iter().find(|m| m.map_url.0 == id))
+        .map_or_else(|| id.to_string(), |m| m.name.0.clone())
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum AgentConfig {
     None,
-    Some(Vec<AgentName>),
+    Some(Vec<AgentId>),
     Random,
-    RandomOf(Vec<AgentName>),
+    RandomOf(Vec<AgentId>),
 }
 
 impl AgentConfig {
@@ -372,7 +997,7 @@ impl AgentConfig {
                 .get()
                 .unwrap()
                 .iter()
-                .filter(|a| agents.contains(&a.name.0))
+                .filter(|a| agents.contains(&a.uuid))
                 .map(|a| a.clone())
                 .collect(),
             AgentConfig::Random => {
@@ -385,7 +1010,7 @@ impl AgentConfig {
                     .get()
                     .unwrap()
                     .iter()
-                    .filter(|a| agents.contains(&a.name.0))
+                    .filter(|a| agents.contains(&a.uuid))
                     .map(|a| a.clone())
                     .collect::<Vec<_>>();
                 agents.shuffle(&mut rand::thread_rng());
@@ -401,7 +1026,7 @@ impl Display for AgentConfig {
             AgentConfig::None => write!(f, "Disabled"),
             AgentConfig::Some(agents) => {
                 for (i, agent) in agents.iter().enumerate() {
-                    write!(f, "{}. {}, ", i + 1, agent)?;
+                    write!(f, "{}. {}, ", i + 1, agent_name(agent))?;
                 }
                 Ok(())
             }
@@ -409,7 +1034,7 @@ impl Display for AgentConfig {
             AgentConfig::RandomOf(agents) => {
                 write!(f, "Random from ")?;
                 for agent in agents {
-                    write!(f, "{}, ", agent)?;
+                    write!(f, "{}, ", agent_name(agent))?;
                 }
                 Ok(())
             }
@@ -423,18 +1048,103 @@ pub enum MapAgentConfig {
     None,
     Default(AgentConfig),
     PerSelectedMap {
-        map_agents: HashMap<MapName, AgentConfig>,
+        map_agents: HashMap<MapId, AgentConfig>,
     },
     DefaultOnSelectedMaps {
         default: AgentConfig,
-        maps: Vec<MapName>,
+        maps: Vec<MapId>,
     },
     PerSelectedMapOrDefault {
         default: AgentConfig,
-        map_agents: HashMap<MapName, AgentConfig>,
+        map_agents: HashMap<MapId, AgentConfig>,
     },
 }
 
+impl MapAgentConfig {
+    /// The agents to lock on the map with the given [`MapId`]
+    pub fn get_agents(&self, map: &str) -> Vec<GameAgent> {
+        self.agent_config(map)
+            .map_or(vec![], |cfg| cfg.get_agents())
+    }
+
+    /// The agent config that applies to the map, `None` if disabled there
+    pub fn agent_config(&self, map: &str) -> Option<&AgentConfig> {
+        match self {
+            MapAgentConfig::None => None,
+            MapAgentConfig::Default(agents) => Some(agents),
+            MapAgentConfig::PerSelectedMap { map_agents } => {
+                map_agents.get(map)
+            }
+            MapAgentConfig::DefaultOnSelectedMaps { default, maps } => {
+                maps.iter().any(|m| m == map).then_some(default)
+            }
+            MapAgentConfig::PerSelectedMapOrDefault {
+                default,
+                map_agents,
+            } => Some(map_agents.get(map).unwrap_or(default)),
+        }
+    }
+}
+
+impl MapAgentConfig {
+    /// Sets the default agents, agents set for single maps are kept
+    pub fn set_default(&mut self, default: AgentConfig) {
+        *self = match std::mem::take(self) {
+            MapAgentConfig::None | MapAgentConfig::Default(_) => {
+                MapAgentConfig::Default(default)
+            }
+            MapAgentConfig::PerSelectedMap { map_agents }
+            | MapAgentConfig::PerSelectedMapOrDefault { map_agents, .. } => {
+                MapAgentConfig::PerSelectedMapOrDefault {
+                    default,
+                    map_agents,
+                }
+            }
+            MapAgentConfig::DefaultOnSelectedMaps { maps, .. } => {
+                MapAgentConfig::DefaultOnSelectedMaps { default, maps }
+            }
+        };
+    }
+
+    /// Sets the agents for a single map, all other maps stay as they are
+    pub fn set_map(&mut self, map: MapId, agents: AgentConfig) {
+        *self = match std::mem::take(self) {
+            MapAgentConfig::None => MapAgentConfig::PerSelectedMap {
+                map_agents: HashMap::from([(map, agents)]),
+            },
+            MapAgentConfig::Default(default) => {
+                MapAgentConfig::PerSelectedMapOrDefault {
+                    default,
+                    map_agents: HashMap::from([(map, agents)]),
+                }
+            }
+            MapAgentConfig::PerSelectedMap { mut map_agents } => {
+                map_agents.insert(map, agents);
+                MapAgentConfig::PerSelectedMap { map_agents }
+            }
+            MapAgentConfig::PerSelectedMapOrDefault {
+                default,
+                mut map_agents,
+            } => {
+                map_agents.insert(map, agents);
+                MapAgentConfig::PerSelectedMapOrDefault {
+                    default,
+                    map_agents,
+                }
+            }
+            // the default must not spread to the maps that weren't selected
+            MapAgentConfig::DefaultOnSelectedMaps { default, maps } => {
+                let mut map_agents: HashMap<_, _> = maps
+                    .into_iter()
+                    .map(|map| (map, default.clone()))
+                    .collect();
+                map_agents.insert(map, agents);
+                MapAgentConfig::PerSelectedMap { map_agents }
+            }
+        };
+    }
+}
+
 impl Display for MapAgentConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -451,7 +1161,7 @@ impl Display for MapAgentConfig {
             MapAgentConfig::PerSelectedMap { map_agents } => {
                 writeln!(f, "Custom agents for select maps = ")?;
                 for (map, cfg) in map_agents {
-                    writeln!(f, "  {}: {}", map, cfg)?;
+                    writeln!(f, "  {}: {}", map_name(map), cfg)?;
                 }
                 Ok(())
             }
@@ -459,7 +1169,7 @@ impl Display for MapAgentConfig {
                 writeln!(f, "Default agents for select maps = {default}")?;
                 write!(f, "  ")?;
                 for map in maps {
-                    write!(f, "{}, ", map)?;
+                    write!(f, "{}, ", map_name(map))?;
                 }
                 Ok(())
             }
@@ -472,7 +1182,7 @@ impl Display for MapAgentConfig {
                     "Custom agents for select maps or default = {default}"
                 )?;
                 for (map, cfg) in map_agents {
-                    writeln!(f, "  {}: {}", map, cfg)?;
+                    writeln!(f, "  {}: {}", map_name(map), cfg)?;
                 }
                 Ok(())
             }
@@ -510,3 +1220,112 @@ impl Display for MapAgentConfigKind {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_profile_file_names() {
+        assert_eq!(
+            profile_from_file_name("config_v1.json").as_deref(),
+            Some(DEFAULT_PROFILE)
+        );
+        assert_eq!(
+            profile_from_file_name("config_v1.solo ranked.json").as_deref(),
+            Some("solo ranked")
+        );
+        assert_eq!(profile_from_file_name("version.json"), None);
+        assert_eq!(profile_from_file_name("config_v1.a.b.json"), None);
+        assert!(validate_profile_name("5-stack with friends").is_ok());
+        assert!(validate_profile_name("../escape").is_err());
+        assert!(validate_profile_name(" ").is_err());
+    }
+
+    #[test]
+    fn test_lock_timing() {
+        let begin = Instant::now();
+        let secs = Duration::from_secs;
+        let end = Some(begin + secs(60));
+        assert_eq!(
+            LockTiming::AfterStart.lock_at(300, begin, end),
+            begin + Duration::from_millis(300)
+        );
+        assert_eq!(
+            LockTiming::BeforeEnd { ms: 5000 }.lock_at(300, begin, end),
+            begin + secs(55)
+        );
+        assert_eq!(
+            LockTiming::AsLateAsPossible { min_left_ms: 2000 }
+                .lock_at(300, begin, end),
+            begin + secs(58)
+        );
+        // not before the start, and the initial delay without a timer
+        assert_eq!(
+            LockTiming::BeforeEnd { ms: 90_000 }.lock_at(300, begin, end),
+            begin
+        );
+        assert_eq!(
+            LockTiming::BeforeEnd { ms: 5000 }.lock_at(300, begin, None),
+            begin + Duration::from_millis(300)
+        );
+    }
+
+    #[test]
+    fn test_set_default_and_map() {
+        let sova = || AgentConfig::Some(vec!["sova".into()]);
+        let mut cfg = MapAgentConfig::None;
+        cfg.set_default(AgentConfig::Random);
+        assert!(matches!(cfg, MapAgentConfig::Default(AgentConfig::Random)));
+        cfg.set_map("ascent".into(), sova());
+        let MapAgentConfig::PerSelectedMapOrDefault {
+            default: AgentConfig::Random,
+            map_agents,
+        } = &cfg
+        else {
+            panic!("unexpected {cfg:?}");
+        };
+        assert!(matches!(map_agents["ascent"], AgentConfig::Some(_)));
+        cfg.set_default(AgentConfig::None);
+        assert!(matches!(
+            cfg,
+            MapAgentConfig::PerSelectedMapOrDefault {
+                default: AgentConfig::None,
+                ..
+            }
+        ));
+
+        let mut cfg = MapAgentConfig::DefaultOnSelectedMaps {
+            default: AgentConfig::Random,
+            maps: vec!["bind".into()],
+        };
+        cfg.set_map("ascent".into(), sova());
+        let MapAgentConfig::PerSelectedMap { map_agents } = &cfg else {
+            panic!("unexpected {cfg:?}");
+        };
+        assert!(matches!(map_agents["bind"], AgentConfig::Random));
+        assert!(matches!(map_agents["ascent"], AgentConfig::Some(_)));
+    }
+
+    #[test]
+    fn test_reload_config() {
+        let path = std::env::temp_dir().join(format!(
+            "valorant-instalock-test-{}.json",
+            std::process::id()
+        ));
+        let (tx, rx) = watch::channel(Config::default());
+        let cfg = Config {
+            instalock_wait_ms: 200,
+            ..Default::default()
+        };
+        migration::write(&path, &cfg).unwrap();
+        assert!(reload_config(&tx, &path).unwrap());
+        assert_eq!(rx.borrow().instalock_wait_ms, 200);
+        // unchanged file, e.g. written by Config::apply
+        assert!(!reload_config(&tx, &path).unwrap());
+        std::fs::write(&path, "{ \"instalock_wait_ms\": ").unwrap();
+        assert!(reload_config(&tx, &path).is_err());
+        assert_eq!(rx.borrow().instalock_wait_ms, 200);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,443 @@
+//! Versioned config file format and migrations from older versions.
+//!
+//! Config files are stored as `{ "version": N, "config": { .. } }`. Version 1
+//! predates this envelope and is the bare config. Migrations work on the raw
+//! json, so old versions of [`Config`] don't have to be kept around.
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use super::Config;
+use crate::{
+    endpoints::EndpointOverrides,
+    global::{self, GAME_AGENTS, GAME_MAPS},
+    valo_types::{GameAgent, GameMap},
+};
+
+/// Version written by this build
+pub const CONFIG_VERSION: u32 = 3;
+
+type Migration = fn(Value, &Roster) -> anyhow::Result<Value>;
+
+/// `MIGRATIONS[i]` upgrades a config of version `i + 1` to `i + 2`
+const MIGRATIONS: [Migration; CONFIG_VERSION as usize - 1] =
+    [v1_to_v2, v2_to_v3];
+
+#[derive(Serialize)]
+struct VersionedConfig<'a> {
+    version: u32,
+    config: &'a Config,
+}
+
+/// Agents and maps to replace the display names stored before v3 with
+pub struct Roster {
+    pub agents: Vec<GameAgent>,
+    pub maps: Vec<GameMap>,
+}
+
+impl Roster {
+    /// The agents and maps fetched at startup, or the cache if they were not
+    /// fetched yet
+    fn load() -> anyhow::Result<Self> {
+        if let (Some(agents), Some(maps)) = (GAME_AGENTS.get(), GAME_MAPS.get())
+        {
+            if !agents.is_empty() && !maps.is_empty() {
+                return Ok(Self {
+                    agents: agents.clone(),
+                    maps: maps.clone(),
+                });
+            }
+        }
+        let (agents, maps) = global::read_cache().context(
+            "The agents and maps are needed, run `cache refresh` while \
+             valorant-api.com is reachable",
+        )?;
+        Ok(Self { agents, maps })
+    }
+}
+
+/// Reads the config at `path`. Older versions are migrated in place, the
+/// original file is kept as `{file}.v{version}.bak`.
+pub fn read(path: &Path) -> anyhow::Result<Config> {
+    read_with(path, Roster::load)
+}
+
+fn read_with(
+    path: &Path,
+    roster: impl FnOnce() -> anyhow::Result<Roster>,
+) -> anyhow::Result<Config> {
+    let bytes = std::fs::read(path)?;
+    let value: Value = serde_json::from_slice(&bytes)?;
+    let version = version_of(&value)?;
+    if version >= CONFIG_VERSION {
+        return migrate(value, version, roster);
+    }
+    // backed up first, so it is kept if the migration fails
+    let backup = backup_path(path, version);
+    std::fs::write(&backup, &bytes)
+        .with_context(|| format!("Failed to back up config to {backup:?}"))?;
+    let config = migrate(value, version, roster)?;
+    write(path, &config)?;
+    eprintln!(
+        "Migrated config from v{version} to v{CONFIG_VERSION}, backup saved to {}",
+        backup.display()
+    );
+    log::info!(
+        "Migrated config {path:?} from v{version} to v{CONFIG_VERSION}, backup: {backup:?}"
+    );
+    Ok(config)
+}
+
+pub fn write(path: &Path, config: &Config) -> anyhow::Result<()> {
+    Ok(std::fs::write(
+        path,
+        serde_json::to_vec_pretty(&VersionedConfig {
+            version: CONFIG_VERSION,
+            config,
+        })?,
+    )?)
+}
+
+/// The endpoint overrides of the config at `path`, without migrating it.
+/// Their format is the same in all versions.
+pub fn read_endpoints(path: &Path) -> anyhow::Result<EndpointOverrides> {
+    let mut value: Value = serde_json::from_slice(&std::fs::read(path)?)?;
+    let config = match version_of(&value)? {
+        1 => &mut value,
+        _ => value
+            .get_mut("config")
+            .context("Config file is missing the config")?,
+    };
+    Ok(match config.get_mut("endpoints").map(Value::take) {
+        Some(endpoints) => serde_json::from_value(endpoints)?,
+        None => EndpointOverrides::default(),
+    })
+}
+
+/// Parses and migrates a config file, also returns the version it was
+/// migrated from if it was outdated
+pub fn parse(bytes: &[u8]) -> anyhow::Result<(Config, Option<u32>)> {
+    parse_with(bytes, Roster::load)
+}
+
+fn parse_with(
+    bytes: &[u8],
+    roster: impl FnOnce() -> anyhow::Result<Roster>,
+) -> anyhow::Result<(Config, Option<u32>)> {
+    let value: Value = serde_json::from_slice(bytes)?;
+    let version = version_of(&value)?;
+    let config = migrate(value, version, roster)?;
+    Ok((config, (version < CONFIG_VERSION).then_some(version)))
+}
+
+/// Runs the migrations from `version` on, `roster` is only loaded if there
+/// are any
+fn migrate(
+    mut value: Value,
+    version: u32,
+    roster: impl FnOnce() -> anyhow::Result<Roster>,
+) -> anyhow::Result<Config> {
+    if version > CONFIG_VERSION {
+        bail!(
+            "Config version v{version} is newer than the supported v{CONFIG_VERSION}"
+        );
+    }
+    if version < CONFIG_VERSION {
+        let roster = roster().with_context(|| {
+            format!("Failed to migrate config from v{version}")
+        })?;
+        for (i, migrate) in
+            MIGRATIONS.iter().enumerate().skip(version as usize - 1)
+        {
+            let (from, to) = (i + 1, i + 2);
+            value = migrate(value, &roster).with_context(|| {
+                format!("Failed to migrate config from v{from} to v{to}")
+            })?;
+            value["version"] = json!(to);
+        }
+    }
+    let config = value
+        .get_mut("config")
+        .map(Value::take)
+        .context("Config file is missing the config")?;
+    Ok(serde_json::from_value(config)?)
+}
+
+fn version_of(value: &Value) -> anyhow::Result<u32> {
+    match value.get("version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v >= 1)
+            .with_context(|| format!("Invalid config version {version}")),
+    }
+}
+
+fn backup_path(path: &Path, version: u32) -> std::path::PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{file_name}.v{version}.bak"))
+}
+
+/// Wraps the bare config into the versioned envelope
+fn v1_to_v2(config: Value, _: &Roster) -> anyhow::Result<Value> {
+    Ok(json!({ "version": 2, "config": config }))
+}
+
+/// Replaces agent and map display names with agent uuids and map urls. Names
+/// that are not in the roster are kept, validation reports them.
+fn v2_to_v3(mut value: Value, roster: &Roster) -> anyhow::Result<Value> {
+    let config = value
+        .get_mut("config")
+        .context("Config file is missing the config")?;
+    if let Some(map_agent_cfg) = config.get_mut("map_agent_config") {
+        resolve_map_agent_config(map_agent_cfg, roster);
+    }
+    if let Some(Value::Object(queues)) = config.get_mut("queue_agent_config") {
+        for map_agent_cfg in queues.values_mut() {
+            resolve_map_agent_config(map_agent_cfg, roster);
+        }
+    }
+    Ok(value)
+}
+
+/// `"None"` or `{ "Kind": .. }`, see [`super::MapAgentConfig`]
+fn resolve_map_agent_config(cfg: &mut Value, roster: &Roster) {
+    let Value::Object(kind) = cfg else {
+        return;
+    };
+    for (kind, fields) in kind.iter_mut() {
+        if kind == "Default" {
+            resolve_agent_config(fields, roster);
+            continue;
+        }
+        if let Some(default) = fields.get_mut("default") {
+            resolve_agent_config(default, roster);
+        }
+        if let Some(Value::Array(maps)) = fields.get_mut("maps") {
+            let mut resolved: Vec<Value> = vec![];
+            for map in maps.drain(..) {
+                let map = match map.as_str() {
+                    Some(name) => json!(map_url(name, roster)),
+                    None => map,
+                };
+                if !resolved.contains(&map) {
+                    resolved.push(map);
+                }
+            }
+            *maps = resolved;
+        }
+        if let Some(Value::Object(map_agents)) = fields.get_mut("map_agents") {
+            *map_agents = std::mem::take(map_agents)
+                .into_iter()
+                .map(|(map, mut agent_cfg)| {
+                    resolve_agent_config(&mut agent_cfg, roster);
+                    (map_url(&map, roster), agent_cfg)
+                })
+                .collect::<Map<_, _>>();
+        }
+    }
+}
+
+/// `"Random"` or `{ "Some": [..] }`, see [`super::AgentConfig`]
+fn resolve_agent_config(cfg: &mut Value, roster: &Roster) {
+    let Value::Object(kind) = cfg else {
+        return;
+    };
+    for agents in kind.values_mut() {
+        let Value::Array(agents) = agents else {
+            continue;
+        };
+        for agent in agents {
+            let Some(name) = agent.as_str() else {
+                continue;
+            };
+            if let Some(known) = roster.agents.iter().find(|a| a.name.0 == name)
+            {
+                *agent = json!(known.uuid);
+            }
+        }
+    }
+}
+
+fn map_url(name: &str, roster: &Roster) -> String {
+    roster
+        .maps
+        .iter()
+        .find(|m| m.name.0 == name)
+        .map_or(name, |m| m.map_url.0.as_str())
+        .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::{AgentConfig, LockStrategy, MapAgentConfig},
+        valo_types,
+    };
+
+    use super::*;
+
+    const V1: &str = include_str!("fixtures/v1.json");
+    const V1_QUEUES: &str = include_str!("fixtures/v1_queues.json");
+    const V2: &str = include_str!("fixtures/v2.json");
+
+    const ASCENT: &str = "/Game/Maps/Ascent/Ascent";
+    const BIND: &str = "/Game/Maps/Duality/Duality";
+
+    fn roster() -> anyhow::Result<Roster> {
+        let agents = ["Jett", "Raze", "Sova"]
+            .into_iter()
+            .map(|name| GameAgent {
+                uuid: name.to_lowercase(),
+                name: valo_types::AgentName(name.to_string()),
+            })
+            .collect();
+        let maps = [("Ascent", ASCENT), ("Bind", BIND)]
+            .into_iter()
+            .map(|(name, map_url)| GameMap {
+                uuid: name.to_lowercase(),
+                name: valo_types::MapName(name.to_string()),
+                map_url: valo_types::MapUrl(map_url.to_string()),
+            })
+            .collect();
+        Ok(Roster { agents, maps })
+    }
+
+    fn offline() -> anyhow::Result<Roster> {
+        bail!("valorant-api.com is unreachable")
+    }
+
+    #[test]
+    fn test_migrate_v1() {
+        let (cfg, migrated_from) = parse_with(V1.as_bytes(), roster).unwrap();
+        assert_eq!(migrated_from, Some(1));
+        assert_eq!(cfg.instalock_wait_ms, 500);
+        let MapAgentConfig::PerSelectedMapOrDefault {
+            default: AgentConfig::Some(default),
+            map_agents,
+        } = cfg.map_agent_config
+        else {
+            panic!("unexpected {:?}", cfg.map_agent_config);
+        };
+        assert_eq!(default, vec!["jett", "raze"]);
+        assert!(matches!(
+            &map_agents[BIND],
+            AgentConfig::RandomOf(agents) if agents == &["raze", "sova"]
+        ));
+        assert_eq!(cfg.lock_strategy, LockStrategy::LockImmediately);
+    }
+
+    #[test]
+    fn test_migrate_v1_queues() {
+        let (cfg, migrated_from) =
+            parse_with(V1_QUEUES.as_bytes(), roster).unwrap();
+        assert_eq!(migrated_from, Some(1));
+        assert_eq!(cfg.endpoints.glz.as_deref(), Some("http://localhost:8080"));
+        assert!(cfg.skip_hovered_agents);
+        assert_eq!(
+            cfg.lock_strategy,
+            LockStrategy::HoverThenLock { delay_ms: 1000 }
+        );
+        assert!(matches!(
+            cfg.queue_agent_config["unrated"],
+            MapAgentConfig::Default(AgentConfig::Random)
+        ));
+    }
+
+    #[test]
+    fn test_migrate_v2() {
+        let (cfg, migrated_from) = parse_with(V2.as_bytes(), roster).unwrap();
+        assert_eq!(migrated_from, Some(2));
+        assert_eq!(cfg.instalock_wait_ms, 300);
+        let MapAgentConfig::DefaultOnSelectedMaps {
+            default: AgentConfig::RandomOf(default),
+            maps,
+        } = cfg.map_agent_config
+        else {
+            panic!("unexpected {:?}", cfg.map_agent_config);
+        };
+        // unknown names are left for validation to report
+        assert_eq!(default, vec!["sova", "Fade"]);
+        assert_eq!(maps, vec![ASCENT, BIND]);
+    }
+
+    #[test]
+    fn test_migration_needs_roster() {
+        let err = parse_with(V2.as_bytes(), offline).unwrap_err();
+        assert!(format!("{err:#}").contains("unreachable"));
+    }
+
+    #[test]
+    fn test_current_version() {
+        let (cfg, _) = parse_with(V1_QUEUES.as_bytes(), roster).unwrap();
+        let bytes = serde_json::to_vec(&VersionedConfig {
+            version: CONFIG_VERSION,
+            config: &cfg,
+        })
+        .unwrap();
+        let (parsed, migrated_from) = parse_with(&bytes, offline).unwrap();
+        assert_eq!(migrated_from, None);
+        assert_eq!(
+            serde_json::to_value(parsed).unwrap(),
+            serde_json::to_value(cfg).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let newer = json!({ "version": CONFIG_VERSION + 1, "config": {} });
+        assert!(parse_with(newer.to_string().as_bytes(), roster).is_err());
+        assert!(
+            parse_with(br#"{ "version": 0, "config": {} }"#, roster).is_err()
+        );
+    }
+
+    #[test]
+    fn test_read_endpoints() {
+        let dir = std::env::temp_dir().join(format!(
+            "valorant-instalock-test-endpoints-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config_v1.json");
+        std::fs::write(&path, V1_QUEUES).unwrap();
+        let endpoints = read_endpoints(&path).unwrap();
+        assert_eq!(endpoints.glz.as_deref(), Some("http://localhost:8080"));
+        std::fs::write(&path, V2).unwrap();
+        assert_eq!(
+            read_endpoints(&path).unwrap(),
+            EndpointOverrides::default()
+        );
+        // read only
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), V2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_read_migrates_in_place() {
+        let dir = std::env::temp_dir()
+            .join(format!("valorant-instalock-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config_v1.json");
+        std::fs::write(&path, V1).unwrap();
+        // the file and its backup are kept if the migration fails
+        assert!(read_with(&path, offline).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), V1);
+        let backup = backup_path(&path, 1);
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), V1);
+        std::fs::remove_file(&backup).unwrap();
+
+        read_with(&path, roster).unwrap();
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), V1);
+        let file: Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(file["version"], json!(CONFIG_VERSION));
+        // nothing left to migrate, no roster needed
+        read_with(&path, offline).unwrap();
+        assert!(!backup_path(&path, CONFIG_VERSION).exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,527 @@
+//! Checks the config against the agents and maps from valorant-api.com.
+//!
+//! Ids that are not part of the current roster are silently skipped when
+//! instalocking, so a typo or a removed agent/map would otherwise just mean
+//! that nothing gets locked.
+use std::{collections::HashMap, fmt::Display};
+
+use console::style;
+
+use crate::{
+    valo_types::{GameAgent, GameMap},
+    DIALOG_THEME,
+};
+
+use super::{
+    map_name, AgentConfig, AgentId, Config, MapAgentConfig, MapId,
+    PromptRandomInstalock, Queue, QueueId, CONFIG,
+};
+
+/// Where an [`AgentConfig`] is located in the config
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// `None` for [`Config::map_agent_config`]
+    pub queue: Option<QueueId>,
+    /// `None` for the default agents
+    pub map: Option<MapId>,
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write_queue(f, self.queue.as_deref())?;
+        match &self.map {
+            Some(map) => write!(f, ", {}", map_name(map)),
+            None => write!(f, ", default"),
+        }
+    }
+}
+
+fn write_queue(
+    f: &mut std::fmt::Formatter<'_>,
+    queue: Option<&str>,
+) -> std::fmt::Result {
+    match queue {
+        None => write!(f, "All Queues"),
+        Some(id) => match Queue::from_id(id) {
+            Some(known) => write!(f, "{known}"),
+            None => write!(f, "{id}"),
+        },
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigIssue {
+    UnknownAgent {
+        location: Location,
+        agent: AgentId,
+    },
+    UnknownMap {
+        queue: Option<QueueId>,
+        map: MapId,
+    },
+    /// None of the configured agents are known, nothing will be locked
+    NoKnownAgents {
+        location: Location,
+    },
+}
+
+impl Display for ConfigIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigIssue::UnknownAgent { location, agent } => {
+                write!(f, "{location}: unknown agent {agent}")
+            }
+            ConfigIssue::UnknownMap { queue, map } => {
+                write_queue(f, queue.as_deref())?;
+                write!(f, ": unknown map {map}")
+            }
+            ConfigIssue::NoKnownAgents { location } => {
+                write!(f, "{location}: no known agents, won't instalock")
+            }
+        }
+    }
+}
+
+/// All entries of `cfg` that don't match the roster. Agents or maps are not
+/// checked if the roster could not be loaded at all.
+pub fn validate(
+    cfg: &Config,
+    agents: &[GameAgent],
+    maps: &[GameMap],
+) -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+    if !maps.is_empty() {
+        for (queue, map_agent_cfg) in map_agent_configs(cfg) {
+            for map in map_names(map_agent_cfg) {
+                if !maps.iter().any(|m| m.map_url.0 == map) {
+                    issues.push(ConfigIssue::UnknownMap {
+                        queue: queue.clone(),
+                        map,
+                    });
+                }
+            }
+        }
+    }
+    if !agents.is_empty() {
+        for (location, agent_cfg) in agent_configs(cfg) {
+            let names = match agent_cfg {
+                AgentConfig::Some(names) | AgentConfig::RandomOf(names) => {
+                    names
+                }
+                AgentConfig::None | AgentConfig::Random => continue,
+            };
+            let mut any_known = false;
+            for name in names.iter() {
+                if agents.iter().any(|a| &a.uuid == name) {
+                    any_known = true;
+                } else {
+                    issues.push(ConfigIssue::UnknownAgent {
+                        location: location.clone(),
+                        agent: name.clone(),
+                    });
+                }
+            }
+            if !any_known {
+                issues.push(ConfigIssue::NoKnownAgents { location });
+            }
+        }
+    }
+    issues
+}
+
+/// Reports the issues of the active config, for when the user can't be
+/// prompted
+pub fn report_active(agents: &[GameAgent], maps: &[GameMap]) {
+    report(&validate(&CONFIG.borrow(), agents, maps));
+}
+
+/// Replaces the agent `from` everywhere in the config, or removes it if `to`
+/// is `None`. Lists left empty are disabled.
+pub fn replace_agent(cfg: &mut Config, from: &str, to: Option<&str>) {
+    for (_, agent_cfg) in agent_configs_mut(cfg) {
+        let names = match agent_cfg {
+            AgentConfig::Some(names) | AgentConfig::RandomOf(names) => names,
+            AgentConfig::None | AgentConfig::Random => continue,
+        };
+        if !names.iter().any(|name| name == from) {
+            continue;
+        }
+        let mut replaced = vec![];
+        for name in names.drain(..) {
+            let name = match (name == from, to) {
+                (false, _) => name,
+                (true, Some(to)) => to.to_string(),
+                (true, None) => continue,
+            };
+            if !replaced.contains(&name) {
+                replaced.push(name);
+            }
+        }
+        *names = replaced;
+        if names.is_empty() {
+            *agent_cfg = AgentConfig::None;
+        }
+    }
+}
+
+/// Replaces the map `from` everywhere in the config, or removes it if `to` is
+/// `None`. Existing entries for `to` are kept.
+pub fn replace_map(cfg: &mut Config, from: &str, to: Option<&str>) {
+    for (_, map_agent_cfg) in map_agent_configs_mut(cfg) {
+        match map_agent_cfg {
+            MapAgentConfig::None | MapAgentConfig::Default(_) => (),
+            MapAgentConfig::PerSelectedMap { map_agents }
+            | MapAgentConfig::PerSelectedMapOrDefault { map_agents, .. } => {
+                replace_map_key(map_agents, from, to)
+            }
+            MapAgentConfig::DefaultOnSelectedMaps { maps, .. } => {
+                if let Some(i) = maps.iter().position(|map| map == from) {
+                    maps.remove(i);
+                    if let Some(to) = to {
+                        if !maps.iter().any(|map| map == to) {
+                            maps.push(to.to_string());
+                            maps.sort();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn replace_map_key(
+    map_agents: &mut HashMap<MapId, AgentConfig>,
+    from: &str,
+    to: Option<&str>,
+) {
+    if let Some(agent_cfg) = map_agents.remove(from) {
+        if let Some(to) = to {
+            map_agents.entry(to.to_string()).or_insert(agent_cfg);
+        }
+    }
+}
+
+fn map_agent_configs(
+    cfg: &Config,
+) -> impl Iterator<Item = (Option<QueueId>, &MapAgentConfig)> {
+    Some((None, &cfg.map_agent_config)).into_iter().chain(
+        cfg.queue_agent_config
+            .iter()
+            .map(|(queue, map_agent_cfg)| (Some(queue.clone()), map_agent_cfg)),
+    )
+}
+
+fn map_agent_configs_mut(
+    cfg: &mut Config,
+) -> Vec<(Option<QueueId>, &mut MapAgentConfig)> {
+    Some((None, &mut cfg.map_agent_config))
+        .into_iter()
+        .chain(
+            cfg.queue_agent_config
+                .iter_mut()
+                .map(|(queue, map_agent_cfg)| {
+                    (Some(queue.clone()), map_agent_cfg)
+                }),
+        )
+        .collect()
+}
+
+fn agent_configs(cfg: &Config) -> Vec<(Location, &AgentConfig)> {
+    let mut out = vec![];
+    for (queue, map_agent_cfg) in map_agent_configs(cfg) {
+        let location = |map: Option<&MapId>| Location {
+            queue: queue.clone(),
+            map: map.cloned(),
+        };
+        match map_agent_cfg {
+            MapAgentConfig::None => (),
+            MapAgentConfig::Default(default)
+            | MapAgentConfig::DefaultOnSelectedMaps { default, .. } => {
+                out.push((location(None), default))
+            }
+            MapAgentConfig::PerSelectedMap { map_agents } => out.extend(
+                map_agents
+                    .iter()
+                    .map(|(map, agent_cfg)| (location(Some(map)), agent_cfg)),
+            ),
+            MapAgentConfig::PerSelectedMapOrDefault {
+                default,
+                map_agents,
+            } => {
+                out.push((location(None), default));
+                out.extend(
+                    map_agents.iter().map(|(map, agent_cfg)| {
+                        (location(Some(map)), agent_cfg)
+                    }),
+                );
+            }
+        }
+    }
+    out
+}
+
+fn agent_configs_mut(cfg: &mut Config) -> Vec<(Location, &mut AgentConfig)> {
+    let mut out = vec![];
+    for (queue, map_agent_cfg) in map_agent_configs_mut(cfg) {
+        let location = |map: Option<&MapId>| Location {
+            queue: queue.clone(),
+            map: map.cloned(),
+        };
+        match map_agent_cfg {
+            MapAgentConfig::None => (),
+            MapAgentConfig::Default(default)
+            | MapAgentConfig::DefaultOnSelectedMaps { default, .. } => {
+                out.push((location(None), default))
+            }
+            MapAgentConfig::PerSelectedMap { map_agents } => out.extend(
+                map_agents
+                    .iter_mut()
+                    .map(|(map, agent_cfg)| (location(Some(map)), agent_cfg)),
+            ),
+            MapAgentConfig::PerSelectedMapOrDefault {
+                default,
+                map_agents,
+            } => {
+                out.push((location(None), default));
+                out.extend(
+                    map_agents.iter_mut().map(|(map, agent_cfg)| {
+                        (location(Some(map)), agent_cfg)
+                    }),
+                );
+            }
+        }
+    }
+    out
+}
+
+fn map_names(map_agent_cfg: &MapAgentConfig) -> Vec<MapId> {
+    match map_agent_cfg {
+        MapAgentConfig::None | MapAgentConfig::Default(_) => vec![],
+        MapAgentConfig::PerSelectedMap { map_agents }
+        | MapAgentConfig::PerSelectedMapOrDefault { map_agents, .. } => {
+            map_agents.keys().cloned().collect()
+        }
+        MapAgentConfig::DefaultOnSelectedMaps { maps, .. } => maps.clone(),
+    }
+}
+
+/// Logs and prints the issues, returns whether there were any
+pub fn report(issues: &[ConfigIssue]) -> bool {
+    if issues.is_empty() {
+        return false;
+    }
+    status_line!(
+        "{}",
+        style("Your config does not match the current agents and maps:")
+            .yellow()
+    );
+    for issue in issues {
+        status_line!("  {issue}");
+        log::warn!("Config issue: {issue}");
+    }
+    true
+}
+
+impl Config {
+    /// Reports entries that don't match the roster and offers to fix them,
+    /// returns the fixed config if the user did
+    pub fn prompt_fix_issues(
+        &self,
+        maps: &[GameMap],
+        agents: &[GameAgent],
+    ) -> Option<Self> {
+        let issues = validate(self, agents, maps);
+        if !report(&issues) {
+            return None;
+        }
+        if !dialoguer::Confirm::with_theme(&*DIALOG_THEME)
+            .with_prompt("Do you want to fix them now?")
+            .default(true)
+            .interact()
+            .unwrap()
+        {
+            return None;
+        }
+        let mut cfg = self.clone();
+        let mut unknown_agents = vec![];
+        let mut unknown_maps = vec![];
+        for issue in issues {
+            match issue {
+                ConfigIssue::UnknownAgent { agent, .. } => {
+                    if !unknown_agents.contains(&agent) {
+                        unknown_agents.push(agent);
+                    }
+                }
+                ConfigIssue::UnknownMap { map, .. } => {
+                    if !unknown_maps.contains(&map) {
+                        unknown_maps.push(map);
+                    }
+                }
+                ConfigIssue::NoKnownAgents { .. } => (),
+            }
+        }
+        for agent in unknown_agents {
+            let items = Some(format!("Remove {agent}"))
+                .into_iter()
+                .chain(agents.iter().map(|a| a.to_string()))
+                .collect::<Vec<_>>();
+            if let Some(i) = dialoguer::Select::with_theme(&*DIALOG_THEME)
+                .with_prompt(format!("Replace unknown agent {agent} with"))
+                .items(&items)
+                .default(0)
+                .interact_opt()
+                .unwrap()
+            {
+                let to = i.checked_sub(1).map(|i| agents[i].uuid.as_str());
+                replace_agent(&mut cfg, &agent, to);
+            }
+        }
+        for map in unknown_maps {
+            let items = Some(format!("Remove {map}"))
+                .into_iter()
+                .chain(maps.iter().map(|m| m.to_string()))
+                .collect::<Vec<_>>();
+            if let Some(i) = dialoguer::Select::with_theme(&*DIALOG_THEME)
+                .with_prompt(format!("Replace unknown map {map} with"))
+                .items(&items)
+                .default(0)
+                .interact_opt()
+                .unwrap()
+            {
+                let to = i.checked_sub(1).map(|i| maps[i].map_url.0.as_str());
+                replace_map(&mut cfg, &map, to);
+            }
+        }
+        // removing agents disables the list, so this is only left for lists
+        // which the user chose to keep
+        for issue in validate(&cfg, agents, maps) {
+            let ConfigIssue::NoKnownAgents { location } = issue else {
+                continue;
+            };
+            eprintln!("{location}:");
+            let map = location.map.as_deref().map(map_name);
+            let Some(new) = Self::prompt_agent_config(
+                agents,
+                map.as_deref(),
+                PromptRandomInstalock::Choose,
+                location.map.is_none(),
+            ) else {
+                continue;
+            };
+            if let Some((_, agent_cfg)) = agent_configs_mut(&mut cfg)
+                .into_iter()
+                .find(|(l, _)| l == &location)
+            {
+                *agent_cfg = new;
+            }
+        }
+        Some(cfg)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::valo_types;
+
+    use super::*;
+
+    fn roster() -> (Vec<GameAgent>, Vec<GameMap>) {
+        let agents = ["Jett", "Raze", "Sova"]
+            .into_iter()
+            .map(|name| GameAgent {
+                uuid: name.to_lowercase(),
+                name: valo_types::AgentName(name.to_string()),
+            })
+            .collect();
+        let maps = ["Ascent", "Bind"]
+            .into_iter()
+            .map(|name| GameMap {
+                uuid: name.to_lowercase(),
+                name: valo_types::MapName(name.to_string()),
+                map_url: valo_types::MapUrl(format!("/Game/Maps/{name}")),
+            })
+            .collect();
+        (agents, maps)
+    }
+
+    const BIND: &str = "/Game/Maps/Bind";
+
+    fn stale_config() -> Config {
+        let mut cfg = Config {
+            map_agent_config: MapAgentConfig::PerSelectedMapOrDefault {
+                default: AgentConfig::Some(vec!["jet".into(), "raze".into()]),
+                map_agents: HashMap::from([
+                    (BIND.into(), AgentConfig::RandomOf(vec!["sova".into()])),
+                    ("/Game/Maps/Fracture".into(), AgentConfig::Random),
+                ]),
+            },
+            ..Default::default()
+        };
+        cfg.queue_agent_config.insert(
+            "unrated".into(),
+            MapAgentConfig::Default(AgentConfig::Some(vec!["jet".into()])),
+        );
+        cfg
+    }
+
+    #[test]
+    fn test_validate() {
+        let (agents, maps) = roster();
+        let issues = validate(&stale_config(), &agents, &maps);
+        let default = Location {
+            queue: None,
+            map: None,
+        };
+        let unrated = Location {
+            queue: Some("unrated".into()),
+            map: None,
+        };
+        assert_eq!(issues.len(), 4, "{issues:#?}");
+        assert!(issues.contains(&ConfigIssue::UnknownMap {
+            queue: None,
+            map: "/Game/Maps/Fracture".into()
+        }));
+        assert!(issues.contains(&ConfigIssue::UnknownAgent {
+            location: default,
+            agent: "jet".into()
+        }));
+        assert!(issues.contains(&ConfigIssue::UnknownAgent {
+            location: unrated.clone(),
+            agent: "jet".into()
+        }));
+        assert!(
+            issues.contains(&ConfigIssue::NoKnownAgents { location: unrated })
+        );
+        // without api data everything would be unknown
+        assert!(validate(&stale_config(), &[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_fix_issues() {
+        let (agents, maps) = roster();
+        let mut cfg = stale_config();
+        replace_agent(&mut cfg, "jet", Some("jett"));
+        replace_map(&mut cfg, "/Game/Maps/Fracture", Some(BIND));
+        assert!(validate(&cfg, &agents, &maps).is_empty());
+        let MapAgentConfig::PerSelectedMapOrDefault {
+            default: AgentConfig::Some(default),
+            map_agents,
+        } = &cfg.map_agent_config
+        else {
+            panic!("unexpected {:?}", cfg.map_agent_config);
+        };
+        assert_eq!(default, &vec!["jett", "raze"]);
+        // the existing entry for Bind wins
+        assert!(matches!(map_agents[BIND], AgentConfig::RandomOf(_)));
+        assert_eq!(map_agents.len(), 1);
+
+        let mut cfg = stale_config();
+        replace_agent(&mut cfg, "jet", None);
+        replace_map(&mut cfg, "/Game/Maps/Fracture", None);
+        assert!(validate(&cfg, &agents, &maps).is_empty());
+        assert!(matches!(
+            cfg.queue_agent_config["unrated"],
+            MapAgentConfig::Default(AgentConfig::None)
+        ));
+    }
+}
@@ -0,0 +1,579 @@
+//! Local HTTP api to control the running instance, e.g. from a stream deck
+//! or a script.
+//!
+//! Only listens on localhost and every request needs the configured token as
+//! `Authorization: Bearer <token>`, or as `?token=` for clients that can't
+//! set headers like `EventSource`. Actions are only sent to the client, like
+//! the console menu they do not wait for the game to apply them.
+//!
+//! Lifecycle events are pushed as server-sent events on `/v1/events` and as
+//! websocket text messages on `/v1/events/ws`, see [`LifecycleEvent`].
+use std::{
+    convert::Infallible,
+    net::{Ipv4Addr, SocketAddr},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Context};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Request, State, WebSocketUpgrade,
+    },
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::{
+    config::{self, validation, Config},
+    events::{self, TimedEvent},
+    global::{GAME_AGENTS, GAME_MAPS},
+    pause::{self, Pause, MAX_PAUSE_MINUTES},
+    valo_types::find_agent,
+    valorant_client::{
+        types::GameLoopState, ClientSnapshot, ValorantClientHandle,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlApiConfig {
+    /// Port on 127.0.0.1, 0 picks a free one. The `pause` and `resume`
+    /// subcommands need a fixed port to reach the running instance.
+    pub port: u16,
+    /// Expected as `Authorization: Bearer <token>`
+    pub token: String,
+}
+
+type SharedClient = Arc<Mutex<Option<ValorantClientHandle>>>;
+
+#[derive(Clone)]
+struct ApiState {
+    token: Arc<str>,
+    client: SharedClient,
+}
+
+struct ApiError(StatusCode, String);
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    profile: String,
+    pause: Pause,
+    /// Whether Valorant is running and the client is initialized
+    connected: bool,
+    #[serde(flatten)]
+    client: Option<ClientSnapshot>,
+    map_name: Option<String>,
+    agent_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AgentRequest {
+    /// Display name or uuid
+    agent: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProfileRequest {
+    profile: String,
+}
+
+/// Pauses until resumed without any field, both fields can't be combined
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PauseRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minutes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pregames: Option<u32>,
+}
+
+/// Starts the api in the background and returns the address it listens on
+pub async fn serve(
+    cfg: &ControlApiConfig,
+    client: SharedClient,
+) -> anyhow::Result<SocketAddr> {
+    if cfg.token.trim().is_empty() {
+        bail!("Refusing to start the control api without a token");
+    }
+    let state = ApiState {
+        token: cfg.token.as_str().into(),
+        client,
+    };
+    let app = Router::new()
+        .route("/v1/status", get(status))
+        .route("/v1/pregame/quit", post(quit_pregame))
+        .route("/v1/game/quit", post(quit_game))
+        .route("/v1/agent/lock", post(lock_agent))
+        .route("/v1/agent/hover", post(hover_agent))
+        .route("/v1/agent/retry", post(retry_agents))
+        .route("/v1/profiles", get(profiles))
+        .route("/v1/profile", post(switch_profile))
+        .route("/v1/pause", get(get_pause).post(set_pause))
+        .route("/v1/resume", post(resume))
+        .route("/v1/events", get(event_source))
+        .route("/v1/events/ws", get(event_socket))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .with_state(state);
+    let listener =
+        tokio::net::TcpListener::bind((Ipv4Addr::LOCALHOST, cfg.port))
+            .await
+            .with_context(|| {
+                format!("Failed to bind control api to port {}", cfg.port)
+            })?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        if let Err(err) = axum::serve(listener, app).await {
+            log::error!("Control api stopped: {err}");
+        }
+    });
+    log::info!("Control api listening on http://{addr}");
+    Ok(addr)
+}
+
+async fn authorize(
+    State(state): State<ApiState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let header_token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let query_token = request.uri().query().and_then(|query| {
+        query
+            .split('&')
+            .find_map(|pair| pair.strip_prefix("token="))
+    });
+    let authorized = header_token
+        .or(query_token)
+        .is_some_and(|token| tokens_match(token, &state.token));
+    if !authorized {
+        return ApiError(StatusCode::UNAUTHORIZED, "Invalid token".into())
+            .into_response();
+    }
+    next.run(request).await
+}
+
+/// Compares every byte regardless of where the first mismatch is, so the
+/// response time doesn't tell how much of a guessed token was right
+fn tokens_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+impl ApiState {
+    fn client(&self) -> ApiResult<ValorantClientHandle> {
+        self.client.lock().unwrap().clone().ok_or_else(|| {
+            ApiError(StatusCode::CONFLICT, "Valorant is not running".into())
+        })
+    }
+
+    /// The client, if it is in agent select
+    fn pregame_client(&self) -> ApiResult<ValorantClientHandle> {
+        let client = self.client()?;
+        match client.status() {
+            Some(status) if status.loop_state == GameLoopState::Pregame => {
+                Ok(client)
+            }
+            _ => Err(ApiError(
+                StatusCode::CONFLICT,
+                "Not in agent select".into(),
+            )),
+        }
+    }
+}
+
+async fn status(State(state): State<ApiState>) -> Json<StatusResponse> {
+    let client = state
+        .client
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(ValorantClientHandle::status);
+    Json(StatusResponse {
+        profile: config::active_profile(),
+        pause: pause::current(),
+        connected: client.is_some(),
+        map_name: client
+            .as_ref()
+            .and_then(|c| c.map.as_deref())
+            .map(config::map_name),
+        agent_name: client
+            .as_ref()
+            .and_then(|c| c.agent.as_deref())
+            .map(config::agent_name),
+        client,
+    })
+}
+
+async fn quit_pregame(State(state): State<ApiState>) -> ApiResult<StatusCode> {
+    state.client()?.quit_pregame().await;
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn quit_game(State(state): State<ApiState>) -> ApiResult<StatusCode> {
+    state.client()?.quit_game().await;
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn lock_agent(
+    State(state): State<ApiState>,
+    Json(request): Json<AgentRequest>,
+) -> ApiResult<StatusCode> {
+    let agent_id = agent_id(&request.agent)?;
+    state.pregame_client()?.lock_agent(agent_id).await;
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn hover_agent(
+    State(state): State<ApiState>,
+    Json(request): Json<AgentRequest>,
+) -> ApiResult<StatusCode> {
+    let agent_id = agent_id(&request.agent)?;
+    state.pregame_client()?.select_agent(agent_id).await;
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Locks the first configured agent teammates did not take
+async fn retry_agents(State(state): State<ApiState>) -> ApiResult<StatusCode> {
+    state.pregame_client()?.retry_fallback().await;
+    Ok(StatusCode::ACCEPTED)
+}
+
+fn agent_id(name: &str) -> ApiResult<config::AgentId> {
+    let agents = GAME_AGENTS.get().map(Vec::as_slice).unwrap_or_default();
+    find_agent(agents, name)
+        .map(|agent| agent.uuid.clone())
+        .map_err(|err| ApiError(StatusCode::BAD_REQUEST, format!("{err:#}")))
+}
+
+async fn profiles() -> Json<Vec<String>> {
+    Json(Config::profiles())
+}
+
+/// Only switches to existing profiles, creating them is left to the console
+async fn switch_profile(
+    Json(request): Json<ProfileRequest>,
+) -> ApiResult<StatusCode> {
+    if !Config::profiles().contains(&request.profile) {
+        return Err(ApiError(
+            StatusCode::NOT_FOUND,
+            format!("Unknown profile '{}'", request.profile),
+        ));
+    }
+    Config::switch_profile(&request.profile).map_err(|err| {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}"))
+    })?;
+    if let (Some(agents), Some(maps)) = (GAME_AGENTS.get(), GAME_MAPS.get()) {
+        validation::report_active(agents, maps);
+    }
+    Ok(StatusCode::OK)
+}
+
+async fn get_pause() -> Json<Pause> {
+    Json(pause::current())
+}
+
+async fn set_pause(
+    Json(request): Json<PauseRequest>,
+) -> ApiResult<Json<Pause>> {
+    let pause = match (request.minutes, request.pregames) {
+        (None, None) => Pause::Paused,
+        (Some(minutes), None) => {
+            Pause::for_minutes(minutes).ok_or_else(|| {
+                ApiError(
+                    StatusCode::BAD_REQUEST,
+                    format!("Pause for at most {MAX_PAUSE_MINUTES} minutes"),
+                )
+            })?
+        }
+        (None, Some(remaining)) => Pause::SkipPregames { remaining },
+        (Some(_), Some(_)) => {
+            return Err(ApiError(
+                StatusCode::BAD_REQUEST,
+                "Pause either for minutes or for pregames".into(),
+            ))
+        }
+    };
+    pause::set(pause);
+    Ok(Json(pause::current()))
+}
+
+async fn resume() -> Json<Pause> {
+    pause::set(Pause::Running);
+    Json(pause::current())
+}
+
+/// Events as they happen, lagging subscribers skip the events they missed
+fn event_stream() -> impl Stream<Item = TimedEvent> {
+    futures::stream::unfold(events::subscribe(), |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => return Some((event, rx)),
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Event subscriber skipped {skipped} events");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+async fn event_source() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = event_stream().map(|event| {
+        Ok(Event::default()
+            .event(<&str>::from(&event.event))
+            .json_data(&event)
+            .unwrap())
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+async fn event_socket(ws: WebSocketUpgrade) -> Response {
+    // subscribe before the upgrade so no event after the handshake is missed
+    let events = event_stream();
+    ws.on_upgrade(|mut socket: WebSocket| async move {
+        let mut events = std::pin::pin!(events);
+        loop {
+            tokio::select! {
+                Some(event) = events.next() => {
+                    let json = serde_json::to_string(&event).unwrap();
+                    if socket.send(Message::Text(json)).await.is_err() {
+                        break;
+                    }
+                }
+                // only used to notice the client going away
+                msg = socket.recv() => match msg {
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => (),
+                },
+                else => break,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mock_server::{self, MockServer};
+
+    use super::*;
+
+    const TOKEN: &str = "test-token";
+
+    async fn start_api(client: SharedClient) -> String {
+        mock_server::init_test_globals().await;
+        let cfg = ControlApiConfig {
+            port: 0,
+            token: TOKEN.to_string(),
+        };
+        let addr = serve(&cfg, client).await.unwrap();
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_requires_token() {
+        let url = start_api(Arc::default()).await;
+        let http = reqwest::Client::new();
+        let res = http.get(format!("{url}/v1/status")).send().await.unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = http
+            .get(format!("{url}/v1/status"))
+            .bearer_auth("wrong")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+        let res = http
+            .get(format!("{url}/v1/status"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let status: serde_json::Value = res.json().await.unwrap();
+        assert_eq!(status["connected"], false);
+        assert!(status["pause"]["mode"].is_string(), "{status}");
+        let res = http
+            .post(format!("{url}/v1/pause"))
+            .bearer_auth(TOKEN)
+            .json(&PauseRequest {
+                minutes: Some(30),
+                pregames: Some(2),
+            })
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = http
+            .post(format!("{url}/v1/pause"))
+            .bearer_auth(TOKEN)
+            .json(&PauseRequest {
+                minutes: Some(u64::MAX),
+                pregames: None,
+            })
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = http
+            .post(format!("{url}/v1/pregame/quit"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::CONFLICT);
+    }
+
+    #[test]
+    fn test_refuses_empty_token() {
+        let cfg = ControlApiConfig {
+            port: 0,
+            token: " ".to_string(),
+        };
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        assert!(rt.block_on(serve(&cfg, Arc::default())).is_err());
+    }
+
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match(TOKEN, TOKEN));
+        assert!(!tokens_match("test-tokem", TOKEN));
+        assert!(!tokens_match("test-toke", TOKEN));
+        assert!(!tokens_match("", TOKEN));
+    }
+
+    #[tokio::test]
+    async fn test_status_and_manual_lock() {
+        let server = MockServer::start().await;
+        server.set_map(mock_server::ASCENT);
+        let handle = mock_server::start(&server, &[]).await;
+        let url = start_api(Arc::new(Mutex::new(Some(handle)))).await;
+        let http = reqwest::Client::new();
+
+        server.enter_pregame("match-1");
+        let status = loop {
+            let status: serde_json::Value = http
+                .get(format!("{url}/v1/status"))
+                .bearer_auth(TOKEN)
+                .send()
+                .await
+                .unwrap()
+                .json()
+                .await
+                .unwrap();
+            if status["loop_state"] == "PREGAME" && !status["map"].is_null() {
+                break status;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        };
+        assert_eq!(status["connected"], true);
+        assert_eq!(status["match_id"], "match-1");
+        assert_eq!(status["map_name"], "Ascent");
+
+        let res = http
+            .post(format!("{url}/v1/agent/lock"))
+            .bearer_auth(TOKEN)
+            .json(&serde_json::json!({ "agent": "raze" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::ACCEPTED);
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.lock_requests(), vec![mock_server::RAZE]);
+
+        let res = http
+            .post(format!("{url}/v1/agent/lock"))
+            .bearer_auth(TOKEN)
+            .json(&serde_json::json!({ "agent": "nobody" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_event_socket() {
+        let server = MockServer::start().await;
+        server.set_map(mock_server::HAVEN);
+        let handle = mock_server::start(&server, &[mock_server::SOVA]).await;
+        let url = start_api(Arc::new(Mutex::new(Some(handle)))).await;
+        let ws_url = format!(
+            "{}/v1/events/ws?token={TOKEN}",
+            url.replace("http://", "ws://")
+        );
+        let (mut socket, _) =
+            tokio_tungstenite::connect_async(ws_url).await.unwrap();
+
+        server.enter_pregame("match-events");
+        // other tests emit events concurrently, only look at ours
+        let mut seen = Vec::new();
+        while let Some(msg) = socket.next().await {
+            let text = msg.unwrap().into_text().unwrap();
+            let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+            let ours = event["match_id"] == "match-events"
+                || event["agent"] == mock_server::SOVA;
+            if !ours {
+                continue;
+            }
+            let kind = event["type"].as_str().unwrap().to_string();
+            if kind == "pregame_entered" {
+                assert_eq!(event["map_name"], "Haven");
+            }
+            seen.push(kind);
+            if seen.last().unwrap() == "lock_succeeded" {
+                break;
+            }
+        }
+        assert_eq!(
+            seen,
+            [
+                "loop_state_changed",
+                "pregame_entered",
+                "lock_attempt",
+                "lock_succeeded"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_event_source() {
+        let url = start_api(Arc::default()).await;
+        let mut res = reqwest::Client::new()
+            .get(format!("{url}/v1/events"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        events::emit(events::LifecycleEvent::LockfileDeleted);
+        let mut body = String::new();
+        while !body.contains("\n\n") {
+            let chunk = res.chunk().await.unwrap().unwrap();
+            body.push_str(std::str::from_utf8(&chunk).unwrap());
+        }
+        assert!(body.starts_with("event: "), "{body}");
+        assert!(body.contains("data: {"), "{body}");
+    }
+}
@@ -0,0 +1,195 @@
+//! Resolves every remote and local URL the application talks to.
+//!
+//! Each endpoint is a URL template that can be overridden from the config
+//! file (`endpoints` section) or from environment variables, with the
+//! environment taking precedence. This allows pointing the whole application
+//! at a local stand-in server, e.g. for integration testing or proxying.
+use serde::{Deserialize, Serialize};
+
+use crate::lockfile::Lockfile;
+
+/// Local Riot Client HTTP API. `{port}` is replaced with the lockfile port.
+pub const DEFAULT_LOCAL_HTTP: &str = "https://127.0.0.1:{port}";
+/// Local Riot Client websocket. `{port}` is replaced with the lockfile port.
+pub const DEFAULT_LOCAL_WEBSOCKET: &str = "wss://127.0.0.1:{port}";
+/// Remote glz API. `{region}` and `{shard}` are taken from the session.
+pub const DEFAULT_GLZ: &str = "https://glz-{region}-1.{shard}.a.pvp.net";
+/// Third party API for agents, maps and the current game version.
+pub const DEFAULT_VALORANT_API: &str = "https://valorant-api.com";
+
+pub const ENV_LOCAL_HTTP: &str = "VALORANT_INSTALOCK_LOCAL_HTTP_URL";
+pub const ENV_LOCAL_WEBSOCKET: &str = "VALORANT_INSTALOCK_LOCAL_WEBSOCKET_URL";
+pub const ENV_GLZ: &str = "VALORANT_INSTALOCK_GLZ_URL";
+pub const ENV_VALORANT_API: &str = "VALORANT_INSTALOCK_VALORANT_API_URL";
+
+/// Optional endpoint overrides as stored in the config file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointOverrides {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_http: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_websocket: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub glz: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valorant_api: Option<String>,
+}
+
+impl EndpointOverrides {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Fully resolved endpoint templates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoints {
+    local_http: String,
+    local_websocket: String,
+    glz: String,
+    valorant_api: String,
+}
+
+impl Default for Endpoints {
+    fn default() -> Self {
+        Self {
+            local_http: DEFAULT_LOCAL_HTTP.into(),
+            local_websocket: DEFAULT_LOCAL_WEBSOCKET.into(),
+            glz: DEFAULT_GLZ.into(),
+            valorant_api: DEFAULT_VALORANT_API.into(),
+        }
+    }
+}
+
+impl Endpoints {
+    /// Resolves the endpoints with the precedence env > config > default.
+    pub fn resolve(overrides: &EndpointOverrides) -> Self {
+        let resolve = |env: &str, cfg: &Option<String>, default: &str| {
+            match std::env::var(env) {
+                Ok(url) if !url.is_empty() => {
+                    log::debug!("Using endpoint from {env}: {url}");
+                    url
+                }
+                _ => match cfg {
+                    Some(url) => {
+                        log::debug!("Using endpoint from config: {url}");
+                        url.clone()
+                    }
+                    None => default.to_string(),
+                },
+            }
+        };
+        Self {
+            local_http: resolve(
+                ENV_LOCAL_HTTP,
+                &overrides.local_http,
+                DEFAULT_LOCAL_HTTP,
+            ),
+            local_websocket: resolve(
+                ENV_LOCAL_WEBSOCKET,
+                &overrides.local_websocket,
+                DEFAULT_LOCAL_WEBSOCKET,
+            ),
+            glz: resolve(ENV_GLZ, &overrides.glz, DEFAULT_GLZ),
+            valorant_api: resolve(
+                ENV_VALORANT_API,
+                &overrides.valorant_api,
+                DEFAULT_VALORANT_API,
+            ),
+        }
+    }
+
+    /// e.g. `https://127.0.0.1:{port}/entitlements/v1/token`
+    pub fn local_http(&self, lockfile: &Lockfile, path: &str) -> String {
+        join(
+            &self
+                .local_http
+                .replace("{port}", &lockfile.port.to_string()),
+            path,
+        )
+    }
+
+    /// e.g. `wss://127.0.0.1:{port}/`
+    pub fn local_websocket(&self, lockfile: &Lockfile) -> String {
+        join(
+            &self
+                .local_websocket
+                .replace("{port}", &lockfile.port.to_string()),
+            "",
+        )
+    }
+
+    /// e.g. `https://glz-{region}-1.{shard}.a.pvp.net/pregame/v1/players/{puuid}`
+    pub fn glz(&self, region: &str, shard: &str, path: &str) -> String {
+        join(
+            &self
+                .glz
+                .replace("{region}", region)
+                .replace("{shard}", shard),
+            path,
+        )
+    }
+
+    /// e.g. `https://valorant-api.com/v1/agents`
+    pub fn valorant_api(&self, path: &str) -> String {
+        join(&self.valorant_api, path)
+    }
+}
+
+fn join(base: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        base.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lockfile() -> Lockfile {
+        Lockfile::parse("Riot Client:1234:50000:password:https").unwrap()
+    }
+
+    #[test]
+    fn test_default_endpoints() {
+        let endpoints = Endpoints::default();
+        assert_eq!(
+            endpoints.local_http(&lockfile(), "entitlements/v1/token"),
+            "https://127.0.0.1:50000/entitlements/v1/token"
+        );
+        assert_eq!(
+            endpoints.local_websocket(&lockfile()),
+            "wss://127.0.0.1:50000/"
+        );
+        assert_eq!(
+            endpoints.glz("eu", "eu", "/pregame/v1/players/puuid"),
+            "https://glz-eu-1.eu.a.pvp.net/pregame/v1/players/puuid"
+        );
+        assert_eq!(
+            endpoints.valorant_api("v1/agents"),
+            "https://valorant-api.com/v1/agents"
+        );
+    }
+
+    #[test]
+    fn test_config_overrides() {
+        let endpoints = Endpoints {
+            glz: "http://localhost:8080/".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            endpoints.glz("eu", "eu", "/pregame/v1/players/puuid"),
+            "http://localhost:8080/pregame/v1/players/puuid"
+        );
+        let overrides = EndpointOverrides {
+            local_http: Some("http://localhost:{port}".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            Endpoints::resolve(&overrides).local_http(&lockfile(), "/x"),
+            "http://localhost:50000/x"
+        );
+    }
+}
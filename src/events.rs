@@ -0,0 +1,96 @@
+//! Lifecycle of the application as events, pushed to external dashboards and
+//! overlays by the control api.
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use strum::IntoStaticStr;
+use tokio::sync::broadcast;
+
+use crate::{
+    config::{AgentId, MapId, QueueId},
+    pause::Pause,
+    valorant_client::{http::CharacterSelectionState, types::GameLoopState},
+};
+
+/// Slow subscribers skip events once this many are queued
+const CAPACITY: usize = 256;
+
+static EVENTS: LazyLock<broadcast::Sender<TimedEvent>> =
+    LazyLock::new(|| broadcast::channel(CAPACITY).0);
+
+/// The `type` tag doubles as the sse event name
+#[derive(Debug, Clone, Serialize, IntoStaticStr)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum LifecycleEvent {
+    LockfileCreated,
+    LockfileDeleted,
+    ClientInitialized {
+        region: String,
+        shard: String,
+    },
+    LoopStateChanged {
+        loop_state: GameLoopState,
+        match_id: Option<String>,
+    },
+    PregameEntered {
+        match_id: Option<String>,
+        map: MapId,
+        map_name: String,
+        queue: Option<QueueId>,
+    },
+    LockAttempt {
+        agent: AgentId,
+        agent_name: String,
+        state: CharacterSelectionState,
+    },
+    LockSucceeded {
+        agent: AgentId,
+        agent_name: String,
+        state: CharacterSelectionState,
+        /// Duration of the request
+        latency_ms: u64,
+    },
+    LockFailed {
+        agent: AgentId,
+        agent_name: String,
+        state: CharacterSelectionState,
+        latency_ms: u64,
+        error: String,
+        /// Riot's `errorCode`, e.g. `CHARACTER_ALREADY_LOCKED`
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_code: Option<String>,
+    },
+    /// None of the configured agents could be locked
+    AllAgentsFailed {
+        match_id: Option<String>,
+        attempts: usize,
+    },
+    Dodged {
+        match_id: Option<String>,
+    },
+    PauseChanged {
+        pause: Pause,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimedEvent {
+    pub time: DateTime<Local>,
+    #[serde(flatten)]
+    pub event: LifecycleEvent,
+}
+
+/// Sends the event to all current subscribers, dropped if there are none
+pub fn emit(event: LifecycleEvent) {
+    log::debug!("Lifecycle event: {event:?}");
+    let _ = EVENTS.send(TimedEvent {
+        time: Local::now(),
+        event,
+    });
+}
+
+pub fn subscribe() -> broadcast::Receiver<TimedEvent> {
+    EVENTS.subscribe()
+}
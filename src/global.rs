@@ -3,6 +3,7 @@ use indicatif::ProgressBar;
 use tokio::sync::OnceCell;
 
 use crate::{
+    endpoints::Endpoints,
     valo_types::{fetch_api_version, GameAgent, GameMap, ValorantApiVersion},
     CACHE_FILES,
 };
@@ -12,11 +13,12 @@ pub static GAME_MAPS: OnceCell<Vec<GameMap>> = OnceCell::const_new();
 pub static GAME_AGENTS: OnceCell<Vec<GameAgent>> = OnceCell::const_new();
 
 async fn init_from_remote(
+    endpoints: &Endpoints,
 ) -> anyhow::Result<(ValorantApiVersion, Vec<GameAgent>, Vec<GameMap>)> {
     let (api_version, agents, maps) = tokio::join!(
-        fetch_api_version(),
-        GameAgent::fetch_all(),
-        GameMap::fetch_all(),
+        fetch_api_version(endpoints),
+        GameAgent::fetch_all(endpoints),
+        GameMap::fetch_all(endpoints),
     );
     log::debug!("init_from_remote");
     log::debug!("api_version: {:#?}", api_version);
@@ -48,9 +50,49 @@ async fn init_from_cache(
     Ok((api_version?, agents?, maps?))
 }
 
-pub async fn init_globals(progress: ProgressBar) {
-    let (api_version, agents, maps) = match init_from_remote().await {
-        Ok(ok) => ok,
+/// The cached agents and maps, for when they are needed before or without
+/// [`init_globals`]
+pub fn read_cache() -> anyhow::Result<(Vec<GameAgent>, Vec<GameMap>)> {
+    let agents = serde_json::from_slice(&std::fs::read(&CACHE_FILES.agents)?)?;
+    let maps = serde_json::from_slice(&std::fs::read(&CACHE_FILES.maps)?)?;
+    Ok((agents, maps))
+}
+
+async fn write_cache(
+    api_version: &ValorantApiVersion,
+    agents: &[GameAgent],
+    maps: &[GameMap],
+) -> anyhow::Result<()> {
+    tokio::try_join!(
+        tokio::fs::write(
+            &CACHE_FILES.api_version,
+            serde_json::to_vec(api_version)?
+        ),
+        tokio::fs::write(&CACHE_FILES.agents, serde_json::to_vec(agents)?),
+        tokio::fs::write(&CACHE_FILES.maps, serde_json::to_vec(maps)?),
+    )?;
+    Ok(())
+}
+
+/// Fetches the agents and maps and saves them as the cache, which is used when
+/// valorant-api.com can't be reached
+pub async fn refresh_cache(
+    endpoints: &Endpoints,
+) -> anyhow::Result<(ValorantApiVersion, Vec<GameAgent>, Vec<GameMap>)> {
+    let (api_version, agents, maps) = init_from_remote(endpoints).await?;
+    write_cache(&api_version, &agents, &maps).await?;
+    Ok((api_version, agents, maps))
+}
+
+pub async fn init_globals(progress: ProgressBar, endpoints: &Endpoints) {
+    let (api_version, agents, maps) = match init_from_remote(endpoints).await {
+        Ok((api_version, agents, maps)) => {
+            // older configs are migrated with the cache
+            if let Err(err) = write_cache(&api_version, &agents, &maps).await {
+                log::warn!("Failed to cache Valorant API data: {err}");
+            }
+            (api_version, agents, maps)
+        }
         Err(err) => {
             log::warn!("Failed to fetch Valorant API data: {err}");
             log::warn!("Attempting to load from cache...");
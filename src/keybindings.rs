@@ -0,0 +1,378 @@
+//! Key chords in the console window mapped to actions, configurable in
+//! [`Config::keybindings`](crate::config::Config::keybindings).
+//!
+//! Chords are written like `shift+tab`, `ctrl+d` or `f5`. Keys are only seen
+//! while the console window has focus.
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context};
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+#[derive(
+    Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum KeyAction {
+    /// The menu with all actions
+    OpenMenu,
+    /// Dodge
+    QuitPregame,
+    QuitGame,
+    ChangeConfig,
+    SwitchProfile,
+    OpenLogFolder,
+    /// Pause or resume instalocking in the next pregames
+    TogglePause,
+    /// Lock the configured agent after the one picked last
+    LockNextAgent,
+    /// Lock the first available configured agent again
+    RetryFallback,
+    /// Lock an agent picked by name
+    LockAgent,
+    /// Hover an agent picked by name
+    HoverAgent,
+    /// Exit the application
+    Quit,
+}
+
+impl KeyAction {
+    /// Actions that ask the user for input, instalock is interrupted meanwhile
+    pub fn prompts(self) -> bool {
+        matches!(
+            self,
+            KeyAction::OpenMenu
+                | KeyAction::ChangeConfig
+                | KeyAction::SwitchProfile
+                | KeyAction::LockAgent
+                | KeyAction::HoverAgent
+        )
+    }
+}
+
+impl Display for KeyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            KeyAction::OpenMenu => "menu",
+            KeyAction::QuitPregame => "dodge",
+            KeyAction::QuitGame => "quit game",
+            KeyAction::ChangeConfig => "config",
+            KeyAction::SwitchProfile => "profile",
+            KeyAction::OpenLogFolder => "logs",
+            KeyAction::TogglePause => "pause",
+            KeyAction::LockNextAgent => "next agent",
+            KeyAction::RetryFallback => "retry",
+            KeyAction::LockAgent => "lock",
+            KeyAction::HoverAgent => "hover",
+            KeyAction::Quit => "quit",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// A key with modifiers, e.g. `ctrl+shift+d`
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyChord {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    key: Key,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+enum Key {
+    /// Always lowercase, shift is kept in [`KeyChord::shift`]
+    Char(char),
+    F(u8),
+    Tab,
+    Enter,
+    Esc,
+    Space,
+    Backspace,
+    Delete,
+    Insert,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+const NAMED_KEYS: [(&str, Key); 16] = [
+    ("tab", Key::Tab),
+    ("enter", Key::Enter),
+    ("esc", Key::Esc),
+    ("space", Key::Space),
+    ("backspace", Key::Backspace),
+    ("delete", Key::Delete),
+    ("insert", Key::Insert),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("escape", Key::Esc),
+];
+
+impl KeyChord {
+    /// `None` for releases and keys that can't be bound
+    pub fn from_event(event: &KeyEvent) -> Option<Self> {
+        if event.kind == KeyEventKind::Release {
+            return None;
+        }
+        let mut shift = event.modifiers.contains(KeyModifiers::SHIFT);
+        let key = match event.code {
+            KeyCode::Char(' ') => Key::Space,
+            KeyCode::Char(c) => {
+                // terminals report shift + d as `D`, sometimes without SHIFT
+                shift |= c.is_uppercase();
+                Key::Char(c.to_ascii_lowercase())
+            }
+            KeyCode::F(n) => Key::F(n),
+            KeyCode::Tab => Key::Tab,
+            KeyCode::BackTab => {
+                shift = true;
+                Key::Tab
+            }
+            KeyCode::Enter => Key::Enter,
+            KeyCode::Esc => Key::Esc,
+            KeyCode::Backspace => Key::Backspace,
+            KeyCode::Delete => Key::Delete,
+            KeyCode::Insert => Key::Insert,
+            KeyCode::Home => Key::Home,
+            KeyCode::End => Key::End,
+            KeyCode::PageUp => Key::PageUp,
+            KeyCode::PageDown => Key::PageDown,
+            KeyCode::Up => Key::Up,
+            KeyCode::Down => Key::Down,
+            KeyCode::Left => Key::Left,
+            KeyCode::Right => Key::Right,
+            _ => return None,
+        };
+        Some(Self {
+            ctrl: event.modifiers.contains(KeyModifiers::CONTROL),
+            alt: event.modifiers.contains(KeyModifiers::ALT),
+            shift,
+            key,
+        })
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chord = KeyChord {
+            ctrl: false,
+            alt: false,
+            shift: false,
+            key: Key::Esc,
+        };
+        let lower = s.trim().to_lowercase();
+        let mut parts: Vec<_> = lower.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|k| !k.is_empty());
+        let key = key.with_context(|| format!("Missing key in '{s}'"))?;
+        for modifier in parts {
+            match modifier {
+                "ctrl" | "control" => chord.ctrl = true,
+                "alt" => chord.alt = true,
+                "shift" => chord.shift = true,
+                _ => bail!("Unknown modifier '{modifier}' in '{s}'"),
+            }
+        }
+        let mut chars = key.chars();
+        chord.key = match (chars.next(), chars.next()) {
+            (Some(c), None) => Key::Char(c),
+            _ => match NAMED_KEYS.iter().find(|(name, _)| *name == key) {
+                Some((_, named)) => *named,
+                None => match key.strip_prefix('f').map(str::parse) {
+                    Some(Ok(n @ 1..=24)) => Key::F(n),
+                    _ => bail!("Unknown key '{key}' in '{s}'"),
+                },
+            },
+        };
+        Ok(chord)
+    }
+}
+
+impl Display for KeyChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "ctrl+")?;
+        }
+        if self.alt {
+            write!(f, "alt+")?;
+        }
+        if self.shift {
+            write!(f, "shift+")?;
+        }
+        match self.key {
+            Key::Char(c) => write!(f, "{c}"),
+            Key::F(n) => write!(f, "f{n}"),
+            named => {
+                let (name, _) =
+                    NAMED_KEYS.iter().find(|(_, key)| *key == named).unwrap();
+                write!(f, "{name}")
+            }
+        }
+    }
+}
+
+impl TryFrom<String> for KeyChord {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<KeyChord> for String {
+    fn from(value: KeyChord) -> Self {
+        value.to_string()
+    }
+}
+
+/// Replaces the defaults completely when set in the config, ctrl+c always
+/// quits the dashboard though
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keybindings(BTreeMap<KeyChord, KeyAction>);
+
+impl Default for Keybindings {
+    /// Actions that can't be undone need a modifier, so a stray keystroke in
+    /// the console window doesn't dodge or quit
+    fn default() -> Self {
+        let bindings = [
+            ("shift+tab", KeyAction::OpenMenu),
+            ("ctrl+shift+d", KeyAction::QuitPregame),
+            ("ctrl+shift+g", KeyAction::QuitGame),
+            ("c", KeyAction::ChangeConfig),
+            ("p", KeyAction::SwitchProfile),
+            ("l", KeyAction::OpenLogFolder),
+            ("ctrl+shift+p", KeyAction::TogglePause),
+            ("ctrl+n", KeyAction::LockNextAgent),
+            ("ctrl+r", KeyAction::RetryFallback),
+            ("a", KeyAction::LockAgent),
+            ("h", KeyAction::HoverAgent),
+            ("ctrl+q", KeyAction::Quit),
+        ];
+        Self(
+            bindings
+                .into_iter()
+                .map(|(chord, action)| (chord.parse().unwrap(), action))
+                .collect(),
+        )
+    }
+}
+
+impl Keybindings {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn action(&self, event: &KeyEvent) -> Option<KeyAction> {
+        self.0.get(&KeyChord::from_event(event)?).copied()
+    }
+
+    /// Bindings ordered by action, for help texts
+    pub fn by_action(&self) -> Vec<(KeyChord, KeyAction)> {
+        let mut bindings: Vec<_> = self
+            .0
+            .iter()
+            .map(|(chord, action)| (*chord, *action))
+            .collect();
+        bindings.sort_by_key(|(chord, action)| (*action, *chord));
+        bindings
+    }
+
+    /// The chord shown for the action, if it is bound at all
+    pub fn chord(&self, action: KeyAction) -> Option<KeyChord> {
+        self.by_action()
+            .into_iter()
+            .find_map(|(chord, a)| (a == action).then_some(chord))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_parse_chords() {
+        for chord in ["shift+tab", "ctrl+alt+shift+d", "f5", "space", "q"] {
+            assert_eq!(chord.parse::<KeyChord>().unwrap().to_string(), chord);
+        }
+        assert_eq!(
+            "Ctrl + D".parse::<KeyChord>().unwrap().to_string(),
+            "ctrl+d"
+        );
+        assert_eq!("escape".parse::<KeyChord>().unwrap().to_string(), "esc");
+        assert!("hyper+d".parse::<KeyChord>().is_err());
+        assert!("ctrl+".parse::<KeyChord>().is_err());
+        assert!("f99".parse::<KeyChord>().is_err());
+    }
+
+    #[test]
+    fn test_match_events() {
+        let bindings = Keybindings::default();
+        // shift + tab arrives as either of these
+        assert_eq!(
+            bindings.action(&press(KeyCode::BackTab, KeyModifiers::SHIFT)),
+            Some(KeyAction::OpenMenu)
+        );
+        assert_eq!(
+            bindings.action(&press(KeyCode::Tab, KeyModifiers::SHIFT)),
+            Some(KeyAction::OpenMenu)
+        );
+        assert_eq!(
+            bindings.action(&press(KeyCode::Char('D'), KeyModifiers::CONTROL)),
+            Some(KeyAction::QuitPregame)
+        );
+        // a stray keystroke doesn't dodge or quit
+        for c in ['d', 'g', 'q', ' '] {
+            assert_eq!(
+                bindings.action(&press(KeyCode::Char(c), KeyModifiers::NONE)),
+                None
+            );
+        }
+        assert_eq!(
+            bindings.action(&press(KeyCode::Char('q'), KeyModifiers::CONTROL)),
+            Some(KeyAction::Quit)
+        );
+
+        let custom: Keybindings =
+            serde_json::from_str(r#"{"ctrl+shift+d": "QuitPregame"}"#).unwrap();
+        assert_eq!(
+            custom.action(&press(KeyCode::Char('D'), KeyModifiers::CONTROL)),
+            Some(KeyAction::QuitPregame)
+        );
+        assert_eq!(
+            custom.action(&press(KeyCode::Char('d'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            serde_json::to_string(&custom).unwrap(),
+            r#"{"ctrl+shift+d":"QuitPregame"}"#
+        );
+    }
+}
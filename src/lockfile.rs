@@ -27,14 +27,6 @@ impl Lockfile {
         })
     }
 
-    pub fn websocket_addr(&self) -> String {
-        format!("wss://127.0.0.1:{}/", self.port)
-    }
-
-    pub fn http_addr(&self) -> String {
-        format!("https://127.0.0.1:{}/", self.port)
-    }
-
     pub fn auth(&self) -> String {
         base64::Engine::encode(
             &base64::engine::general_purpose::STANDARD,
@@ -91,7 +83,7 @@ pub async fn watch_lockfile() -> anyhow::Result<Receiver<LockfileEvent>> {
             let msg = match watcher_rx.recv().await {
                 Some(ok) => ok,
                 None => {
-                    eprintln!(
+                    status_line!(
                         "Underlying Lockfile watcher stopped unexpectedly."
                     );
                     Err(notify::Error::generic("watcher stopped unexpectedly"))
@@ -110,7 +102,7 @@ pub async fn watch_lockfile() -> anyhow::Result<Receiver<LockfileEvent>> {
                         {
                             Ok(ok) => ok,
                             Err(err) => {
-                                eprintln!("There was an error reading the Valorant lockfile. Can't start listening to Valorant events.");
+                                status_line!("There was an error reading the Valorant lockfile. Can't start listening to Valorant events.");
                                 log::error!("could not read lockfile {err}");
                                 continue;
                             }
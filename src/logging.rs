@@ -1,10 +1,29 @@
-use std::io::BufWriter;
+use std::{
+    collections::VecDeque,
+    io::BufWriter,
+    sync::{LazyLock, Mutex},
+};
 
 use anyhow::bail;
+use chrono::{DateTime, Local};
 use env_logger::Logger;
 
 use crate::{built_info, LOG_DIR};
 
+/// Lines kept for the dashboard's log pane
+const TAIL_LEN: usize = 200;
+
+static LOG_TAIL: LazyLock<Mutex<VecDeque<TailLine>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::with_capacity(TAIL_LEN)));
+
+#[derive(Debug, Clone)]
+pub struct TailLine {
+    pub time: DateTime<Local>,
+    /// `None` for console output, see [`crate::tui::status_line`]
+    pub level: Option<log::Level>,
+    pub message: String,
+}
+
 // this will be "info" in release mode and "debug" in debug mode
 const DEFAULT_LOG_LEVEL: &'static str = {
     let mut i = 0;
@@ -22,26 +41,34 @@ const DEFAULT_LOG_LEVEL: &'static str = {
     out
 };
 
-pub fn init_logging() {
+/// Logs to a new file in [`LOG_DIR`], or to stdout if `stdout` is set. Falls
+/// back to the terminal if the file can't be created.
+pub fn init_logging(stdout: bool) {
     let _ = std::fs::create_dir_all(&*LOG_DIR);
     tokio::task::spawn_blocking(|| {
         let _ = purge_old_logs();
     });
-    let mut use_file_logger = true;
-    let logger = file_logger().unwrap_or_else(|_| {
-        use_file_logger = false;
-        env_logger::Builder::from_env(
+    let file_logger = match stdout {
+        true => None,
+        false => file_logger().ok(),
+    };
+    let use_file_logger = file_logger.is_some();
+    let logger = file_logger.unwrap_or_else(|| {
+        let mut builder = env_logger::Builder::from_env(
             env_logger::Env::default().default_filter_or(DEFAULT_LOG_LEVEL),
-        )
-        .build()
+        );
+        if stdout {
+            builder.target(env_logger::Target::Stdout);
+        }
+        builder.build()
     });
 
     let max_level = logger.filter();
-    let r = log::set_boxed_logger(Box::new(logger));
+    let r = log::set_boxed_logger(Box::new(TailLogger(logger)));
     if r.is_ok() {
         log::set_max_level(max_level);
     }
-    if !use_file_logger {
+    if !use_file_logger && !stdout {
         log::warn!("Failed to create log file, using terminal instead");
     }
     if let Some(level) = max_level.to_level() {
@@ -49,6 +76,50 @@ pub fn init_logging() {
     }
 }
 
+/// Adds a line to the log tail, dropping the oldest one if it is full
+pub fn push_tail(level: Option<log::Level>, message: String) {
+    let mut tail = LOG_TAIL.lock().unwrap();
+    if tail.len() == TAIL_LEN {
+        tail.pop_front();
+    }
+    tail.push_back(TailLine {
+        time: Local::now(),
+        level,
+        message,
+    });
+}
+
+/// The last `n` lines, oldest first
+pub fn tail(n: usize) -> Vec<TailLine> {
+    let tail = LOG_TAIL.lock().unwrap();
+    tail.iter()
+        .skip(tail.len().saturating_sub(n))
+        .cloned()
+        .collect()
+}
+
+/// Also keeps our own records in the log tail, dependencies are too noisy
+struct TailLogger(Logger);
+
+impl log::Log for TailLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.0.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.0.matches(record)
+            && record.target().starts_with(env!("CARGO_CRATE_NAME"))
+        {
+            push_tail(Some(record.level()), record.args().to_string());
+        }
+        self.0.log(record);
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
 fn purge_old_logs() -> anyhow::Result<()> {
     let log_files = std::fs::read_dir(&*LOG_DIR)?;
     for file in log_files {
@@ -1,5 +1,4 @@
 #![feature(lazy_cell)]
-#![feature(fs_try_exists)]
 
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
@@ -11,10 +10,10 @@ use std::time::Duration;
 use anyhow::bail;
 use anyhow::Context;
 use anyhow::Result;
+use clap::Parser;
 use config::Config;
+use config::LockTiming;
 use crossterm::event;
-use crossterm::event::KeyCode;
-use crossterm::event::KeyEventKind;
 use dialoguer::console::style;
 use dialoguer::theme::ColorfulTheme;
 use directories::ProjectDirs;
@@ -22,19 +21,39 @@ use futures::FutureExt;
 use futures::StreamExt;
 use indicatif::ProgressBar;
 
+use crate::endpoints::Endpoints;
 use crate::global::API_VERSION;
 use crate::global::GAME_AGENTS;
 use crate::global::GAME_MAPS;
+use crate::keybindings::KeyAction;
 use crate::lockfile::watch_lockfile;
+use crate::pause::{Pause, MAX_PAUSE_MINUTES};
 use crate::valorant_client::ValorantClientHandle;
 
+/// `eprintln!` that goes to the log pane instead while the dashboard is open
+macro_rules! status_line {
+    ($($arg:tt)*) => {
+        $crate::tui::status_line(format!($($arg)*))
+    };
+}
+
+mod cli;
 mod config;
+mod control_api;
+mod endpoints;
+mod events;
 mod global;
+mod keybindings;
 mod locale;
 mod lockfile;
 mod logging;
+#[cfg(test)]
+mod mock_server;
+mod pause;
+mod tui;
 mod valo_types;
 mod valorant_client;
+mod webhooks;
 
 /// VALORANT API DOCS: https://valapidocs.techchrism.me/endpoint/sessions
 
@@ -71,8 +90,15 @@ pub static INTERRUPT: AtomicBool = AtomicBool::new(false);
 
 pub static DONT_SAVE_CONFIG: AtomicBool = AtomicBool::new(false);
 
-pub static CONFIG: tokio::sync::OnceCell<Config> =
-    tokio::sync::OnceCell::const_new();
+/// Running as a background service, nothing may wait for console input
+pub static HEADLESS: AtomicBool = AtomicBool::new(false);
+
+/// The active config, swapped when switching profiles
+pub static CONFIG: LazyLock<tokio::sync::watch::Sender<Config>> =
+    LazyLock::new(|| tokio::sync::watch::Sender::new(Config::default()));
+
+pub static ACTIVE_PROFILE: LazyLock<Mutex<String>> =
+    LazyLock::new(|| Mutex::new(config::DEFAULT_PROFILE.to_string()));
 
 pub struct CacheFiles {
     pub agents: PathBuf,
@@ -105,8 +131,11 @@ fn init_config() -> anyhow::Result<Config> {
     Ok(match Config::read() {
         Ok(ok) => ok,
         Err(err) => {
-            eprintln!("{}", style("Failed to read config file!").red());
+            eprintln!("{} {err:#}", style("Failed to read config file!").red());
             log::warn!("Failed to read config file: {err}");
+            if HEADLESS.load(std::sync::atomic::Ordering::Relaxed) {
+                bail!("Failed to read config file: {err}")
+            }
             if dialoguer::Confirm::with_theme(&*DIALOG_THEME)
                 .with_prompt(
                     "Do you want to proceed with a new temporary config?",
@@ -124,20 +153,359 @@ fn init_config() -> anyhow::Result<Config> {
     })
 }
 
-#[tokio::main]
-async fn main() -> Result<(), anyhow::Error> {
-    let dbg_build = if built_info::PROFILE.eq("debug") {
-        " (DEBUG BUILD)"
+/// Offers to fix config entries that don't match the fetched agents and maps
+fn fix_config_issues() {
+    let (agents, maps) = (GAME_AGENTS.get().unwrap(), GAME_MAPS.get().unwrap());
+    if HEADLESS.load(std::sync::atomic::Ordering::Relaxed) {
+        config::validation::report_active(agents, maps);
+        return;
+    }
+    let cfg = CONFIG.borrow().clone();
+    let Some(cfg) = cfg.prompt_fix_issues(maps, agents) else {
+        return;
+    };
+    status_line!("Fixed config:");
+    status_line!("{}", cfg.describe_agent_config());
+    if let Err(err) = cfg.apply() {
+        status_line!(
+            "{}",
+            style(format!("Failed to save config: {err}")).red()
+        );
+        log::error!("Failed to save fixed config: {err}");
+    }
+}
+
+/// Resolves on Ctrl-C, and on SIGTERM on unix
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => (),
+                    _ = sigterm.recv() => (),
+                }
+                return;
+            }
+            Err(err) => log::warn!("Failed to listen for SIGTERM: {err}"),
+        }
+    }
+    if let Err(err) = tokio::signal::ctrl_c().await {
+        log::warn!("Failed to listen for Ctrl-C: {err}");
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Runs the actions bound to keys in the console window, returns `true`
+/// when the user quits
+fn spawn_console_keys(
+    client: Arc<Mutex<Option<ValorantClientHandle>>>,
+) -> tokio::task::JoinHandle<bool> {
+    tokio::task::spawn(async move {
+        let mut stream = event::EventStream::new();
+        while let Some(event) = stream.next().fuse().await {
+            match event {
+                Ok(event::Event::Key(key_event)) => {
+                    let action = CONFIG.borrow().keybindings.action(&key_event);
+                    match action {
+                        Some(KeyAction::Quit) => {
+                            log::info!("Quit by key in console");
+                            return true;
+                        }
+                        Some(action) => run_key_action(action, &client).await,
+                        None => (),
+                    }
+                }
+                Err(err) => log::warn!("Error in console event stream: {err}"),
+                _ => (),
+            }
+        }
+        false
+    })
+}
+
+/// Runs the action of a key binding, instalock is interrupted while the
+/// action prompts. [`KeyAction::Quit`] is up to the caller.
+async fn run_key_action(
+    action: KeyAction,
+    client: &Mutex<Option<ValorantClientHandle>>,
+) {
+    let prompts = action.prompts();
+    if prompts {
+        status_line!("Application Interrupted/Paused");
+        log::warn!("Interrupted for {action} at {}", chrono::Local::now());
+        INTERRUPT.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+    let (action, from_menu) = match action {
+        KeyAction::OpenMenu => (prompt_menu(), true),
+        action => (Some(action), false),
+    };
+    match action {
+        None | Some(KeyAction::OpenMenu | KeyAction::Quit) => (),
+        Some(KeyAction::QuitPregame) => {
+            log::info!("Selected Quit Pregame");
+            quit_pregame(client).await;
+        }
+        Some(KeyAction::QuitGame) => {
+            log::info!("Selected Quit Ingame");
+            quit_game(client).await;
+        }
+        Some(KeyAction::ChangeConfig) => change_config_menu(),
+        Some(KeyAction::SwitchProfile) => switch_profile_menu(),
+        Some(KeyAction::OpenLogFolder) => open_log_folder(),
+        // the menu asks how long to pause, the key pauses until resumed
+        Some(KeyAction::TogglePause)
+            if from_menu && pause::current().is_running() =>
+        {
+            pause_menu()
+        }
+        Some(KeyAction::TogglePause) => pause::toggle(),
+        Some(KeyAction::LockNextAgent) => {
+            log::info!("Selected Lock Next Agent");
+            let send_client = client.lock().unwrap().clone();
+            match send_client {
+                Some(client) => client.lock_next_agent().await,
+                None => {
+                    log::warn!("No ValorantClient available to lock next agent")
+                }
+            }
+        }
+        Some(KeyAction::RetryFallback) => {
+            log::info!("Selected Retry Configured Agents");
+            let send_client = client.lock().unwrap().clone();
+            match send_client {
+                Some(client) => client.retry_fallback().await,
+                None => {
+                    log::warn!("No ValorantClient available to retry agents")
+                }
+            }
+        }
+        Some(KeyAction::LockAgent) => pick_agent_menu(client, true).await,
+        Some(KeyAction::HoverAgent) => pick_agent_menu(client, false).await,
+    }
+    if prompts {
+        INTERRUPT.store(false, std::sync::atomic::Ordering::Relaxed);
+        status_line!("Application Resumed");
+        log::warn!("Resuming from Interrupt at {}", chrono::Local::now());
+    }
+}
+
+/// The action to run, `None` if the menu was closed
+fn prompt_menu() -> Option<KeyAction> {
+    let pause = match pause::current().is_running() {
+        true => "Pause Instalock",
+        false => "Resume Instalock",
+    };
+    let items = [
+        ("Quit Pregame (Dodge)", KeyAction::QuitPregame),
+        ("Quit Ingame", KeyAction::QuitGame),
+        ("Lock Agent", KeyAction::LockAgent),
+        ("Hover Agent", KeyAction::HoverAgent),
+        ("Lock Next Agent", KeyAction::LockNextAgent),
+        ("Retry Configured Agents", KeyAction::RetryFallback),
+        (pause, KeyAction::TogglePause),
+        ("Change Config", KeyAction::ChangeConfig),
+        ("Switch Profile", KeyAction::SwitchProfile),
+        ("Open Log Folder", KeyAction::OpenLogFolder),
+    ];
+    let i = dialoguer::Select::with_theme(&*DIALOG_THEME)
+        .items(&items.map(|(item, _)| item))
+        .interact_opt()
+        .unwrap()?;
+    Some(items[i].1)
+}
+
+/// Locks or hovers an agent searched by name, e.g. to correct a bad
+/// auto-pick or while instalock is paused
+async fn pick_agent_menu(
+    client: &Mutex<Option<ValorantClientHandle>>,
+    lock: bool,
+) {
+    let agents = GAME_AGENTS.get().unwrap();
+    let prompt = match lock {
+        true => "Lock Agent",
+        false => "Hover Agent",
+    };
+    let Some(i) = dialoguer::FuzzySelect::with_theme(&*DIALOG_THEME)
+        .with_prompt(prompt)
+        .items(agents)
+        .interact_opt()
+        .unwrap()
+    else {
+        return;
+    };
+    let agent = &agents[i];
+    log::info!("Selected {prompt} {}", agent.name);
+    let send_client = client.lock().unwrap().clone();
+    match send_client {
+        Some(client) if lock => client.lock_agent(agent.uuid.clone()).await,
+        Some(client) => client.select_agent(agent.uuid.clone()).await,
+        None => log::warn!("No ValorantClient available to pick an agent"),
+    }
+}
+
+fn pause_menu() {
+    let items = [
+        "Until resumed",
+        "Skip the next pregames",
+        "For some minutes",
+    ];
+    let Some(i) = dialoguer::Select::with_theme(&*DIALOG_THEME)
+        .with_prompt("Pause Instalock")
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        .unwrap()
+    else {
+        return;
+    };
+    let pause = match i {
+        0 => Pause::Paused,
+        1 => Pause::SkipPregames {
+            remaining: dialoguer::Input::with_theme(&*DIALOG_THEME)
+                .with_prompt("Pregames to skip")
+                .default(1)
+                .interact()
+                .unwrap(),
+        },
+        _ => {
+            let minutes: u64 = dialoguer::Input::with_theme(&*DIALOG_THEME)
+                .with_prompt("Minutes to pause")
+                .default(30)
+                .validate_with(|minutes: &u64| {
+                    if *minutes <= MAX_PAUSE_MINUTES {
+                        Ok(())
+                    } else {
+                        Err(format!("At most {MAX_PAUSE_MINUTES} minutes"))
+                    }
+                })
+                .interact()
+                .unwrap();
+            Pause::for_minutes(minutes).unwrap()
+        }
+    };
+    pause::set(pause);
+}
+
+async fn quit_pregame(client: &Mutex<Option<ValorantClientHandle>>) {
+    let send_client = client.lock().unwrap().clone();
+    if let Some(client) = send_client {
+        client.quit_pregame().await;
     } else {
-        ""
+        log::warn!("No ValorantClient available to quit pregame");
+    }
+}
+
+async fn quit_game(client: &Mutex<Option<ValorantClientHandle>>) {
+    let send_client = client.lock().unwrap().clone();
+    if let Some(client) = send_client {
+        client.quit_game().await;
+    } else {
+        log::warn!("No ValorantClient available to quit ingame");
+    }
+}
+
+/// Prompts for the config entry to change and its new value
+fn change_config_menu() {
+    let items = [
+        "Edit agents",
+        "Edit lock timing",
+        "Edit skipping agents hovered by teammates",
+        "Edit lock strategy",
+    ];
+    let i = dialoguer::Select::with_theme(&*DIALOG_THEME)
+        .items(&items)
+        .interact_opt()
+        .unwrap();
+    if i == Some(0) {
+        if let Some(cfg) = Config::prompt_map_agent_cfg(
+            Some(CONFIG.borrow().clone()),
+            GAME_MAPS.get().unwrap(),
+            GAME_AGENTS.get().unwrap(),
+        ) {
+            cfg.apply().unwrap();
+            status_line!("New config:");
+            status_line!("{}", cfg.describe_agent_config());
+            status_line!("");
+            status_line!(
+                "{}",
+                style("Changes will be applied to the next pregame.").yellow()
+            );
+        }
+    } else if i == Some(1) {
+        let cfg = Config::prompt_lock_timing(Some(CONFIG.borrow().clone()));
+        cfg.apply().unwrap();
+        status_line!("New lock timing: {}", cfg.lock_timing);
+        if cfg.lock_timing == LockTiming::AfterStart {
+            status_line!(
+                "New initial Instalock delay: {}ms",
+                cfg.instalock_wait_ms
+            );
+        }
+        status_line!("");
+        status_line!(
+            "{}",
+            style("Changes will be applied to the next pregame.").yellow()
+        );
+    } else if i == Some(2) {
+        let cfg =
+            Config::prompt_skip_hovered_agents(Some(CONFIG.borrow().clone()));
+        cfg.apply().unwrap();
+        status_line!(
+            "Skip agents hovered by teammates: {}",
+            cfg.skip_hovered_agents
+        );
+        status_line!("");
+        status_line!(
+            "{}",
+            style("Changes will be applied to the next pregame.").yellow()
+        );
+    } else if i == Some(3) {
+        let cfg = Config::prompt_lock_strategy(Some(CONFIG.borrow().clone()));
+        cfg.apply().unwrap();
+        status_line!("New lock strategy: {}", cfg.lock_strategy);
+        status_line!("");
+        status_line!(
+            "{}",
+            style("Changes will be applied to the next pregame.").yellow()
+        );
+    }
+}
+
+fn switch_profile_menu() {
+    let Some(profile) = Config::prompt_profile() else {
+        return;
     };
-    println!(
-        "valorant-instalock v{}{} made by {}\n",
-        built_info::PKG_VERSION,
-        dbg_build,
-        built_info::PKG_AUTHORS
-    );
-    logging::init_logging();
+    match Config::switch_profile(&profile) {
+        Ok(cfg) => {
+            status_line!("Switched to profile {profile}:");
+            status_line!("{}", cfg.describe_agent_config());
+            fix_config_issues();
+        }
+        Err(err) => {
+            status_line!(
+                "{}",
+                style(format!("Failed to switch to profile {profile}: {err}"))
+                    .red()
+            );
+            log::error!("Failed to switch to profile {profile}: {err}");
+        }
+    }
+}
+
+fn open_log_folder() {
+    if let Err(err) = open::that_detached(&*LOG_DIR) {
+        status_line!("Failed to open log folder: {err}");
+        log::error!("Failed to open log folder: {err}");
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), anyhow::Error> {
+    let cli = cli::Cli::parse();
+    HEADLESS.store(cli.headless, std::sync::atomic::Ordering::Relaxed);
+    logging::init_logging(cli.log_stdout);
     let _ = std::fs::create_dir_all(PROJECT_DIRS.cache_dir());
     let _ = std::fs::create_dir_all(PROJECT_DIRS.config_dir());
     match tokio::fs::read_to_string(&CONFIG_FILES.version).await {
@@ -153,17 +521,65 @@ async fn main() -> Result<(), anyhow::Error> {
         // version equals current version
         Ok(_) => (),
     }
-    CONFIG.set(init_config()?).unwrap();
-
-    let progress = ProgressBar::new_spinner();
-    progress.enable_steady_tick(Duration::from_millis(75));
-    global::init_globals(progress.clone()).await;
+    *ACTIVE_PROFILE.lock().unwrap() = cli.profile;
+    match cli.command {
+        None | Some(cli::Command::Run) => (),
+        Some(cli::Command::OneOff(command)) => return cli::run(command).await,
+    }
+    let dbg_build = if built_info::PROFILE.eq("debug") {
+        " (DEBUG BUILD)"
+    } else {
+        ""
+    };
+    println!(
+        "valorant-instalock v{}{} made by {}\n",
+        built_info::PKG_VERSION,
+        dbg_build,
+        built_info::PKG_AUTHORS
+    );
+    let progress = if cli.headless {
+        ProgressBar::hidden()
+    } else {
+        let progress = ProgressBar::new_spinner();
+        progress.enable_steady_tick(Duration::from_millis(75));
+        progress
+    };
+    // before reading the config, migrating older versions needs the agents
+    // and maps
+    global::init_globals(
+        progress.clone(),
+        &Endpoints::resolve(&Config::read_endpoints()),
+    )
+    .await;
     progress.println(format!("{}", API_VERSION.get().unwrap()));
+    if cli.headless {
+        log::info!("{}", API_VERSION.get().unwrap());
+    }
     progress.finish();
+    CONFIG.send_replace(init_config()?);
+    if let Err(err) = config::watch_config() {
+        log::warn!("Failed to watch config file, external edits require a restart: {err}");
+    }
+    fix_config_issues();
+    let _webhooks_task = webhooks::spawn();
+    if cli.paused {
+        pause::set(Pause::Paused);
+    }
     let mut lockfile_watcher = watch_lockfile().await?;
     let valorant_client: Arc<Mutex<Option<ValorantClientHandle>>> =
         Arc::new(Mutex::new(None));
-    let menu_valorant_client = Arc::clone(&valorant_client);
+    let control_api = CONFIG.borrow().control_api.clone();
+    if let Some(api_cfg) = control_api {
+        if let Err(err) =
+            control_api::serve(&api_cfg, Arc::clone(&valorant_client)).await
+        {
+            eprintln!(
+                "{}",
+                style(format!("Failed to start control api: {err:#}")).red()
+            );
+            log::error!("Failed to start control api: {err:#}");
+        }
+    }
     // TODO: FIXME when opening program when game already running, check if player is in pregame already!!!!!
     // https://valapidocs.techchrism.me/endpoint/pre-game-player to get pregame match id
     //
@@ -171,118 +587,61 @@ async fn main() -> Result<(), anyhow::Error> {
     // add menu option for Dodge Pregame
 
     // TODO: Add ability to open log file via menu entry
-    eprintln!("For menu options or to interrupt/pause the application, press shift + tab in the console window");
-    let interrupt_task = tokio::task::spawn(async move {
-        let mut stream = event::EventStream::new();
-        while let Some(event) = stream.next().fuse().await {
-            match event {
-                //Ok(event::Event::Key(event)) => eprintln!("{:#?}", event),
-                Ok(event::Event::Key(key_event))
-                    if (key_event.code == KeyCode::BackTab
-                        || key_event.code == KeyCode::Tab)
-                        && key_event.kind == KeyEventKind::Press
-                        && key_event
-                            .modifiers
-                            .intersects(event::KeyModifiers::SHIFT) =>
-                {
-                    eprintln!("Application Interrupted/Paused by shift + tab");
-                    log::warn!(
-                        "Interrupted by shift + tab at {}",
-                        chrono::Local::now()
-                    );
-                    INTERRUPT.store(true, std::sync::atomic::Ordering::Relaxed);
-                    let items = [
-                        "Quit Pregame (Dodge)",
-                        "Quit Ingame",
-                        "Change Config",
-                        "Open Log Folder",
-                    ];
-                    if let Some(i) =
-                        dialoguer::Select::with_theme(&*DIALOG_THEME)
-                            .items(&items)
-                            .interact_opt()
-                            .unwrap()
-                    {
-                        if i == 0 {
-                            log::info!("Selected Quit Pregame in Menu");
-                            let send_client =
-                                menu_valorant_client.lock().unwrap().clone();
-                            if let Some(client) = send_client {
-                                client.quit_pregame().await;
-                            } else {
-                                log::warn!("No ValorantClient available to quit pregame");
-                            }
-                        } else if i == 1 {
-                            log::info!("Selected Quit Ingame in Menu");
-                            let send_client =
-                                menu_valorant_client.lock().unwrap().clone();
-                            if let Some(client) = send_client {
-                                client.quit_game().await;
-                            } else {
-                                log::warn!("No ValorantClient available to quit ingame");
-                            }
-                        } else if i == 2 {
-                            let items =
-                                ["Edit agents", "Edit initial instalock delay"];
-                            let i =
-                                dialoguer::Select::with_theme(&*DIALOG_THEME)
-                                    .items(&items)
-                                    .interact_opt()
-                                    .unwrap();
-                            if i == Some(0) {
-                                if let Some(cfg) = Config::prompt_map_agent_cfg(
-                                    Some(CONFIG.get().unwrap().clone()),
-                                    GAME_MAPS.get().unwrap(),
-                                    GAME_AGENTS.get().unwrap(),
-                                ) {
-                                    cfg.write().unwrap();
-                                    eprintln!("New config:");
-                                    eprintln!("{}", cfg.map_agent_config);
-                                    eprintln!("");
-                                    eprintln!("{}", style("Changes will be applied after restarting the application.").yellow());
-                                }
-                            } else if i == Some(1) {
-                                let cfg = Config::prompt_instalock_wait_ms(
-                                    Some(CONFIG.get().unwrap().clone()),
-                                );
-                                cfg.write().unwrap();
-                                eprintln!(
-                                    "New initial Instalock delay: {}ms",
-                                    cfg.instalock_wait_ms
-                                );
-                                eprintln!("");
-                                eprintln!("{}", style("Changes will be applied after restarting the application.").yellow());
-                            }
-                        } else if i == 3 {
-                            if let Err(err) = open::that_detached(&*LOG_DIR) {
-                                eprintln!("Failed to open log folder: {err}");
-                                log::error!("Failed to open log folder: {err}");
-                            }
-                        }
-                    }
-                    INTERRUPT
-                        .store(false, std::sync::atomic::Ordering::Relaxed);
-                    eprintln!("Application Resumed");
-                    log::warn!(
-                        "Resuming from Interrupt at {}",
-                        chrono::Local::now()
-                    );
-                }
-                Err(err) => log::warn!("Error in console event stream: {err}"),
-                _ => (),
+    // log lines on stdout would be drawn over by the dashboard
+    let dashboard = if cli.headless
+        || cli.plain
+        || cli.log_stdout
+        || !console::user_attended()
+    {
+        None
+    } else {
+        match tui::spawn(Arc::clone(&valorant_client)) {
+            Ok(task) => Some(task),
+            Err(err) => {
+                log::warn!("Failed to open dashboard, using plain console output: {err:#}");
+                None
             }
         }
+    };
+    let plain_console = !cli.headless && dashboard.is_none();
+    let menu_chord = CONFIG.borrow().keybindings.chord(KeyAction::OpenMenu);
+    if let (true, Some(chord)) = (plain_console, menu_chord) {
+        eprintln!("For menu options or to interrupt/pause the application, press {chord} in the console window");
+    }
+    let console_task = dashboard.or_else(|| {
+        plain_console.then(|| spawn_console_keys(Arc::clone(&valorant_client)))
     });
 
+    // quitting from the console shuts down like a signal
+    let mut shutdown = std::pin::pin!(async move {
+        match console_task {
+            Some(console_task) => tokio::select! {
+                _ = shutdown_signal() => (),
+                Ok(true) = console_task => (),
+            },
+            None => shutdown_signal().await,
+        }
+    });
+    let mut shutdown_requested = false;
     loop {
-        match lockfile_watcher.recv().await {
+        let event = tokio::select! {
+            event = lockfile_watcher.recv() => event,
+            _ = &mut shutdown => {
+                status_line!("Shutting down");
+                log::info!("Received shutdown signal, shutting down");
+                shutdown_requested = true;
+                break;
+            }
+        };
+        match event {
             Some(lockfile::LockfileEvent::Created(lockfile)) => {
                 log::info!("Lockfile created/modified: {lockfile:?}",);
+                events::emit(events::LifecycleEvent::LockfileCreated);
                 log::info!("Starting ValorantClient");
                 *valorant_client.lock().unwrap() = Some(
                     match ValorantClientHandle::start(
                         lockfile,
-                        CONFIG.get().unwrap().clone(),
+                        CONFIG.subscribe(),
                     )
                     .await
                     {
@@ -298,6 +657,7 @@ async fn main() -> Result<(), anyhow::Error> {
             }
             Some(lockfile::LockfileEvent::Deleted) => {
                 log::info!("Lockfile deleted",);
+                events::emit(events::LifecycleEvent::LockfileDeleted);
                 *valorant_client.lock().unwrap() = None;
             }
             None => {
@@ -309,7 +669,12 @@ async fn main() -> Result<(), anyhow::Error> {
 
     //let _ = tokio::join!(interrupt_task);
 
-    if console::user_attended_stderr() {
+    tui::restore();
+    // closes the websocket of the client
+    *valorant_client.lock().unwrap() = None;
+    log::logger().flush();
+
+    if !cli.headless && !shutdown_requested && console::user_attended_stderr() {
         eprintln!("");
         eprintln!("Press Enter to exit...");
         std::io::stdin().read_line(&mut String::new()).unwrap();
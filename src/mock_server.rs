@@ -0,0 +1,835 @@
+//! Scriptable stand-in for everything the application talks to: the local
+//! Riot Client (HTTP + websocket), the remote glz API and valorant-api.com.
+//!
+//! Tests point the client at it through [`MockServer::endpoint_overrides`]
+//! and drive the game loop with [`MockServer::enter_pregame`] and friends.
+use std::{collections::HashSet, net::SocketAddr, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket},
+        Path, Request, State, WebSocketUpgrade,
+    },
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use futures::{SinkExt, StreamExt};
+use parking_lot::Mutex;
+use serde_json::{json, Value};
+use strum::VariantArray;
+use tokio::sync::{broadcast, watch, OnceCell};
+
+use crate::{
+    config::{AgentConfig, Config, MapAgentConfig},
+    endpoints::{EndpointOverrides, Endpoints},
+    global,
+    lockfile::Lockfile,
+    valorant_client::{
+        types::{EventKind, GameLoopState},
+        ValorantClientHandle,
+    },
+};
+
+pub const SUBJECT: &str = "3e62cdbc-c4d0-5408-9de0-74bd1555f4cb";
+pub const PASSWORD: &str = "mock-password";
+
+pub const JETT: &str = "add6443a-41bd-e414-f6ad-e58d267f4e95";
+pub const RAZE: &str = "f94c3b30-42be-e959-889c-5aa313dba261";
+pub const REYNA: &str = "a3bfb853-43b2-7238-a4f1-ad90e9e46bcc";
+pub const SOVA: &str = "320b2a48-4d9b-a075-30f1-1f93a9b638fa";
+
+pub const ASCENT: &str = "/Game/Maps/Ascent/Ascent";
+pub const BIND: &str = "/Game/Maps/Duality/Duality";
+pub const HAVEN: &str = "/Game/Maps/Triad/Triad";
+
+const AGENTS: [(&str, &str); 4] = [
+    (JETT, "Jett"),
+    (RAZE, "Raze"),
+    (REYNA, "Reyna"),
+    (SOVA, "Sova"),
+];
+
+const MAPS: [(&str, &str, &str); 3] = [
+    ("7eaecc1b-4337-bbf6-6ab9-04b8f06b3319", "Ascent", ASCENT),
+    ("2c9d57ec-4431-9c5e-2939-8f9ef6dd5cba", "Bind", BIND),
+    ("2bee0dc9-4ffe-519b-1cbd-7fbe763a6047", "Haven", HAVEN),
+];
+
+/// Initializes the global agents, maps and version once per test binary by
+/// fetching them from a mock server.
+pub async fn init_test_globals() {
+    static INIT: OnceCell<()> = OnceCell::const_new();
+    INIT.get_or_init(|| async {
+        let server = MockServer::start().await;
+        global::init_globals(
+            indicatif::ProgressBar::hidden(),
+            &Endpoints::resolve(&server.endpoint_overrides()),
+        )
+        .await;
+    })
+    .await;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    pub method: String,
+    pub path: String,
+}
+
+#[derive(Debug)]
+struct MockState {
+    access_token: String,
+    entitlements_token: String,
+    loop_state: GameLoopState,
+    match_id: Option<String>,
+    map_url: String,
+    queue_id: String,
+    /// `PhaseTimeRemainingNS` of every pregame match response
+    phase_time_remaining: Duration,
+    /// agents for which the lock request fails with 409
+    rejected_agents: HashSet<String>,
+    /// lock requests answered with 429 before any succeeds
+    rate_limited_locks: u32,
+    /// lock requests answered with 503 before any succeeds
+    failed_locks: u32,
+    /// the next lock is applied, but only answered after this long
+    slow_lock: Option<Duration>,
+    /// glz requests fail with 401 even with the current tokens
+    tokens_revoked: bool,
+    locked_agent: Option<String>,
+    hovered_agent: Option<String>,
+    /// (subject, agent id, "selected" or "locked")
+    teammates: Vec<(String, String, &'static str)>,
+    subscriptions: Vec<String>,
+    requests: Vec<MockRequest>,
+}
+
+type SharedState = Arc<Mutex<MockState>>;
+
+pub struct MockServer {
+    pub addr: SocketAddr,
+    state: SharedState,
+    events: broadcast::Sender<String>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Client config locking `agents` on every map against `server`.
+pub fn config(server: &MockServer, agents: &[&str]) -> Config {
+    Config {
+        instalock_wait_ms: 0,
+        map_agent_config: MapAgentConfig::Default(AgentConfig::Some(
+            agents.iter().map(|a| a.to_string()).collect(),
+        )),
+        endpoints: server.endpoint_overrides(),
+        ..Default::default()
+    }
+}
+
+/// Starts a client against `server` locking `agents` on every map.
+pub async fn start(
+    server: &MockServer,
+    agents: &[&str],
+) -> ValorantClientHandle {
+    start_with(server, config(server, agents)).await.0
+}
+
+/// Starts a client against `server` once it is subscribed to the events.
+/// The returned sender swaps the config of the running client.
+pub async fn start_with(
+    server: &MockServer,
+    config: Config,
+) -> (ValorantClientHandle, watch::Sender<Config>) {
+    init_test_globals().await;
+    let (config_tx, config_rx) = watch::channel(config);
+    let handle = ValorantClientHandle::start(server.lockfile(), config_rx)
+        .await
+        .unwrap();
+    server.wait_for_subscriptions().await;
+    (handle, config_tx)
+}
+
+impl MockServer {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState {
+            access_token: "access-token-1".into(),
+            entitlements_token: "entitlements-token-1".into(),
+            loop_state: GameLoopState::Menus,
+            match_id: None,
+            map_url: ASCENT.into(),
+            queue_id: "competitive".into(),
+            phase_time_remaining: Duration::from_secs(80),
+            rejected_agents: HashSet::new(),
+            rate_limited_locks: 0,
+            failed_locks: 0,
+            slow_lock: None,
+            tokens_revoked: false,
+            locked_agent: None,
+            hovered_agent: None,
+            teammates: vec![],
+            subscriptions: vec![],
+            requests: vec![],
+        }));
+        let (events, _) = broadcast::channel(100);
+        let app = Router::new()
+            .route("/", get(websocket))
+            .route("/entitlements/v1/token", get(entitlements_token))
+            .route(
+                "/product-session/v1/external-sessions",
+                get(external_sessions),
+            )
+            .route("/pregame/v1/players/:subject", get(pregame_player))
+            .route("/pregame/v1/matches/:match_id", get(pregame_match))
+            .route(
+                "/pregame/v1/matches/:match_id/lock/:agent_id",
+                post(lock_agent),
+            )
+            .route(
+                "/pregame/v1/matches/:match_id/select/:agent_id",
+                post(select_agent),
+            )
+            .route("/pregame/v1/matches/:match_id/quit", post(quit_pregame))
+            .route("/core-game/v1/players/:subject", get(ingame_player))
+            .route(
+                "/core-game/v1/players/:subject/disassociate/:match_id",
+                post(quit_ingame),
+            )
+            .route("/v1/version", get(api_version))
+            .route("/v1/agents", get(api_agents))
+            .route("/v1/maps", get(api_maps))
+            .layer(middleware::from_fn_with_state(
+                Arc::clone(&state),
+                record_request,
+            ))
+            .with_state((Arc::clone(&state), events.clone()));
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let task = tokio::task::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        Self {
+            addr,
+            state,
+            events,
+            task,
+        }
+    }
+
+    pub fn lockfile(&self) -> Lockfile {
+        Lockfile {
+            name: "Riot Client".into(),
+            pid: 1,
+            port: self.addr.port() as u32,
+            password: PASSWORD.into(),
+            protocol: "https".into(),
+        }
+    }
+
+    pub fn endpoint_overrides(&self) -> EndpointOverrides {
+        let base = format!("http://{}", self.addr);
+        EndpointOverrides {
+            local_http: Some("http://127.0.0.1:{port}".into()),
+            local_websocket: Some("ws://127.0.0.1:{port}".into()),
+            glz: Some(base.clone()),
+            valorant_api: Some(base),
+        }
+    }
+
+    /// Waits until the client subscribed to all events it listens to
+    pub async fn wait_for_subscriptions(&self) {
+        self.wait_until(|state| {
+            state.subscriptions.len() >= EventKind::VARIANTS.len()
+        })
+        .await;
+    }
+
+    pub fn set_map(&self, map_url: &str) {
+        self.state.lock().map_url = map_url.into();
+    }
+
+    /// Empty string for custom games
+    pub fn set_queue(&self, queue_id: &str) {
+        self.state.lock().queue_id = queue_id.into();
+    }
+
+    pub fn set_phase_time_remaining(&self, remaining: Duration) {
+        self.state.lock().phase_time_remaining = remaining;
+    }
+
+    /// Makes every lock request for this agent fail
+    pub fn reject_lock(&self, agent_id: &str) {
+        self.state.lock().rejected_agents.insert(agent_id.into());
+    }
+
+    /// Issues new tokens without telling the client, like a missed
+    /// `EntitlementsTokenChanged` event
+    pub fn rotate_tokens(&self, n: u32) {
+        let mut state = self.state.lock();
+        state.access_token = format!("access-token-{n}");
+        state.entitlements_token = format!("entitlements-token-{n}");
+    }
+
+    /// Fails all glz requests with 401, new tokens don't help either
+    pub fn revoke_tokens(&self) {
+        self.state.lock().tokens_revoked = true;
+    }
+
+    /// Answers the next `count` lock requests with 429 and `Retry-After: 0`
+    pub fn rate_limit_locks(&self, count: u32) {
+        self.state.lock().rate_limited_locks = count;
+    }
+
+    /// Answers the next `count` lock requests with 503
+    pub fn fail_locks(&self, count: u32) {
+        self.state.lock().failed_locks = count;
+    }
+
+    /// Applies the next lock request but answers it only after `delay`, like
+    /// a response that is lost. Locking again is rejected with 409.
+    pub fn lock_slowly(&self, delay: Duration) {
+        self.state.lock().slow_lock = Some(delay);
+    }
+
+    /// Adds a teammate to the ally team who hovered or locked an agent
+    pub fn add_teammate(&self, subject: &str, agent_id: &str, locked: bool) {
+        self.state.lock().teammates.push((
+            subject.into(),
+            agent_id.into(),
+            if locked { "locked" } else { "selected" },
+        ));
+    }
+
+    pub fn enter_pregame(&self, match_id: &str) {
+        self.set_loop_state(GameLoopState::Pregame, Some(match_id));
+    }
+
+    pub fn enter_ingame(&self, match_id: &str) {
+        self.set_loop_state(GameLoopState::Ingame, Some(match_id));
+    }
+
+    pub fn enter_menus(&self) {
+        self.set_loop_state(GameLoopState::Menus, None);
+    }
+
+    fn set_loop_state(
+        &self,
+        loop_state: GameLoopState,
+        match_id: Option<&str>,
+    ) {
+        {
+            let mut state = self.state.lock();
+            state.loop_state = loop_state;
+            state.match_id = match_id.map(Into::into);
+            if loop_state != GameLoopState::Pregame {
+                state.locked_agent = None;
+                state.hovered_agent = None;
+            }
+        }
+        let payload = json!({
+            "subject": SUBJECT,
+            "cxnState": "CONNECTED",
+            "loopState": match loop_state {
+                GameLoopState::Pregame => "PREGAME",
+                GameLoopState::Ingame => "INGAME",
+                GameLoopState::Menus => "MENUS",
+            },
+            "loopStateMetadata": match_id.unwrap_or_default(),
+        });
+        self.emit(json!([8, "OnJsonApiEvent_riot-messaging-service_v1_message", {
+            "data": {
+                "ackRequired": false,
+                "id": "",
+                "payload": payload.to_string(),
+                "resource": format!("ares-session/v1/sessions/{SUBJECT}"),
+                "service": "session",
+                "timestamp": chrono::Utc::now().timestamp_millis(),
+                "version": "1",
+            },
+            "eventType": "Update",
+            "uri": format!("/riot-messaging-service/v1/message/ares-session/v1/sessions/{SUBJECT}"),
+        }]));
+    }
+
+    fn emit(&self, event: Value) {
+        let _ = self.events.send(event.to_string());
+    }
+
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().requests.clone()
+    }
+
+    /// Agent ids of all lock requests in the order they were received
+    pub fn lock_requests(&self) -> Vec<String> {
+        self.agent_requests("/lock/")
+    }
+
+    /// Agent ids of all select (hover) requests in the order they were
+    /// received
+    pub fn select_requests(&self) -> Vec<String> {
+        self.agent_requests("/select/")
+    }
+
+    fn agent_requests(&self, action: &str) -> Vec<String> {
+        self.requests()
+            .into_iter()
+            .filter(|req| req.method == "POST")
+            .filter_map(|req| {
+                req.path
+                    .split_once(action)
+                    .map(|(_, agent)| agent.to_string())
+            })
+            .collect()
+    }
+
+    pub fn hovered_agent(&self) -> Option<String> {
+        self.state.lock().hovered_agent.clone()
+    }
+
+    pub fn locked_agent(&self) -> Option<String> {
+        self.state.lock().locked_agent.clone()
+    }
+
+    /// Waits for `count` requests whose path contains `pattern`
+    pub async fn wait_for_requests(&self, pattern: &str, count: usize) {
+        self.wait_until(|state| {
+            state
+                .requests
+                .iter()
+                .filter(|req| req.path.contains(pattern))
+                .count()
+                >= count
+        })
+        .await;
+    }
+
+    async fn wait_until(&self, f: impl Fn(&MockState) -> bool) {
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(5);
+        while !f(&self.state.lock()) {
+            if tokio::time::Instant::now() > deadline {
+                panic!("timed out waiting for mock server state");
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+}
+
+type AppState = (SharedState, broadcast::Sender<String>);
+
+async fn record_request(
+    State(state): State<SharedState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    state.lock().requests.push(MockRequest {
+        method: req.method().to_string(),
+        path: req.uri().path().to_string(),
+    });
+    next.run(req).await
+}
+
+fn check_local_auth(headers: &HeaderMap) -> Result<(), StatusCode> {
+    let expected = format!(
+        "Basic {}",
+        base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            format!("riot:{PASSWORD}"),
+        )
+    );
+    match headers.get(http::header::AUTHORIZATION) {
+        Some(value) if value.as_bytes() == expected.as_bytes() => Ok(()),
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+fn check_remote_auth(
+    state: &MockState,
+    headers: &HeaderMap,
+) -> Result<(), StatusCode> {
+    let bearer = format!("Bearer {}", state.access_token);
+    match (
+        headers.get(http::header::AUTHORIZATION),
+        headers.get("X-Riot-Entitlements-JWT"),
+    ) {
+        (Some(auth), Some(entitlements))
+            if !state.tokens_revoked
+                && auth.as_bytes() == bearer.as_bytes()
+                && entitlements.as_bytes()
+                    == state.entitlements_token.as_bytes() =>
+        {
+            Ok(())
+        }
+        _ => Err(StatusCode::UNAUTHORIZED),
+    }
+}
+
+fn riot_error(status: StatusCode, error_code: &str) -> Response {
+    (
+        status,
+        Json(json!({
+            "httpStatus": status.as_u16(),
+            "errorCode": error_code,
+            "message": format!("mock error: {error_code}"),
+        })),
+    )
+        .into_response()
+}
+
+async fn websocket(
+    State((state, events)): State<AppState>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    if let Err(status) = check_local_auth(&headers) {
+        return status.into_response();
+    }
+    let events = events.subscribe();
+    ws.on_upgrade(move |socket| handle_socket(socket, state, events))
+}
+
+async fn handle_socket(
+    socket: WebSocket,
+    state: SharedState,
+    mut events: broadcast::Receiver<String>,
+) {
+    let (mut tx, mut rx) = socket.split();
+    loop {
+        tokio::select! {
+            msg = rx.next() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    // [5, "OnJsonApiEvent_..."]
+                    if let Ok((5, kind)) =
+                        serde_json::from_str::<(u8, String)>(&text)
+                    {
+                        state.lock().subscriptions.push(kind);
+                    }
+                }
+                Some(Ok(_)) => (),
+                _ => break,
+            },
+            event = events.recv() => match event {
+                Ok(event) => {
+                    if tx.send(Message::Text(event)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => (),
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+        }
+    }
+}
+
+async fn entitlements_token(
+    State((state, _)): State<AppState>,
+    headers: HeaderMap,
+) -> Response {
+    if let Err(status) = check_local_auth(&headers) {
+        return status.into_response();
+    }
+    let state = state.lock();
+    Json(json!({
+        "accessToken": state.access_token,
+        "entitlements": [],
+        "issuer": "https://entitlements.auth.riotgames.com",
+        "subject": SUBJECT,
+        "token": state.entitlements_token,
+    }))
+    .into_response()
+}
+
+async fn external_sessions(headers: HeaderMap) -> Response {
+    if let Err(status) = check_local_auth(&headers) {
+        return status.into_response();
+    }
+    Json(json!({
+        "host_app": {
+            "launchConfiguration": { "arguments": [] },
+            "version": "99.0.0",
+            "productId": "riot_client",
+        },
+        "valorant": {
+            "launchConfiguration": {
+                "arguments": [
+                    "-config-endpoint=https://shared.eu.a.pvp.net",
+                    "-ares-deployment=eu",
+                ],
+            },
+            "version": "release-08.05-shipping-13-2404755",
+            "productId": "valorant",
+        },
+    }))
+    .into_response()
+}
+
+async fn pregame_player(
+    State((state, _)): State<AppState>,
+    headers: HeaderMap,
+    Path(_subject): Path<String>,
+) -> Response {
+    let state = state.lock();
+    if let Err(status) = check_remote_auth(&state, &headers) {
+        return status.into_response();
+    }
+    match (&state.loop_state, &state.match_id) {
+        (GameLoopState::Pregame, Some(match_id)) => Json(json!({
+            "Subject": SUBJECT,
+            "MatchID": match_id,
+            "Version": 1,
+        }))
+        .into_response(),
+        _ => riot_error(StatusCode::NOT_FOUND, "RESOURCE_NOT_FOUND"),
+    }
+}
+
+async fn pregame_match(
+    State((state, _)): State<AppState>,
+    headers: HeaderMap,
+    Path(match_id): Path<String>,
+) -> Response {
+    let state = state.lock();
+    if let Err(status) = check_remote_auth(&state, &headers) {
+        return status.into_response();
+    }
+    if state.loop_state != GameLoopState::Pregame
+        || state.match_id.as_ref() != Some(&match_id)
+    {
+        return riot_error(StatusCode::NOT_FOUND, "MATCH_NOT_FOUND");
+    }
+    let (character_id, selection_state) =
+        match (&state.locked_agent, &state.hovered_agent) {
+            (Some(agent), _) => (agent.as_str(), "locked"),
+            (None, Some(agent)) => (agent.as_str(), "selected"),
+            (None, None) => ("", ""),
+        };
+    let players = std::iter::once((SUBJECT, character_id, selection_state))
+        .chain(state.teammates.iter().map(|(subject, agent, selection)| {
+            (subject.as_str(), agent.as_str(), *selection)
+        }))
+        .map(|(subject, character_id, selection_state)| {
+            json!({
+                "Subject": subject,
+                "CharacterID": character_id,
+                "CharacterSelectionState": selection_state,
+                "PregamePlayerState": "joined",
+                "CompetitiveTier": 0,
+                "IsCaptain": false,
+            })
+        })
+        .collect::<Vec<_>>();
+    Json(json!({
+        "ID": match_id,
+        "Version": 1,
+        "Teams": [],
+        "AllyTeam": {
+            "TeamID": "Blue",
+            "Players": players,
+        },
+        "EnemyTeam": null,
+        "ObserverSubjects": [],
+        "MatchCoaches": [],
+        "EnemyTeamSize": 5,
+        "EnemyTeamLockCount": 0,
+        "PregameState": "character_select_active",
+        "LastUpdated": "2024-04-01T12:00:00Z",
+        "MapID": state.map_url,
+        "MapSelectPool": [],
+        "BannedMapIDs": [],
+        "CastedVotes": {},
+        "MapSelectSteps": [],
+        "MapSelectStep": 0,
+        "Team1": "Blue",
+        "GamePodID": "aresriot.aws-rclusterprod-euc1-1.eu-gp-frankfurt-1",
+        "Mode": "/Game/GameModes/Bomb/BombGameMode.BombGameMode_C",
+        "VoiceSessionID": "",
+        "MUCName": "",
+        "TeamMatchToken": "",
+        "QueueID": state.queue_id,
+        "ProvisioningFlowID": if state.queue_id.is_empty() {
+            "CustomGame"
+        } else {
+            "Matchmaking"
+        },
+        "IsRanked": state.queue_id == "competitive",
+        "PhaseTimeRemainingNS": state.phase_time_remaining.as_nanos() as u64,
+        "StepTimeRemainingNS": 0,
+        "altModesFlagADA": false,
+        "TournamentMetadata": null,
+        "RosterMetadata": null,
+    }))
+    .into_response()
+}
+
+async fn lock_agent(
+    State((state, _)): State<AppState>,
+    headers: HeaderMap,
+    Path((match_id, agent_id)): Path<(String, String)>,
+) -> Response {
+    let delay = {
+        let mut state = state.lock();
+        if let Err(status) = check_remote_auth(&state, &headers) {
+            return status.into_response();
+        }
+        if state.loop_state != GameLoopState::Pregame
+            || state.match_id.as_ref() != Some(&match_id)
+        {
+            return riot_error(StatusCode::NOT_FOUND, "MATCH_NOT_FOUND");
+        }
+        if state.locked_agent.is_some() {
+            return riot_error(StatusCode::CONFLICT, "ALREADY_LOCKED");
+        }
+        if state.rate_limited_locks > 0 {
+            state.rate_limited_locks -= 1;
+            let mut res =
+                riot_error(StatusCode::TOO_MANY_REQUESTS, "RATE_LIMITED");
+            res.headers_mut().insert(
+                http::header::RETRY_AFTER,
+                HeaderValue::from_static("0"),
+            );
+            return res;
+        }
+        if state.failed_locks > 0 {
+            state.failed_locks -= 1;
+            return StatusCode::SERVICE_UNAVAILABLE.into_response();
+        }
+        if state.rejected_agents.contains(&agent_id) {
+            return riot_error(
+                StatusCode::CONFLICT,
+                "CHARACTER_ALREADY_LOCKED",
+            );
+        }
+        state.locked_agent = Some(agent_id.clone());
+        if state.slow_lock.is_some() {
+            state.rejected_agents.insert(agent_id);
+        }
+        state.slow_lock.take()
+    };
+    // the lock is applied, but the response only arrives after the delay
+    if let Some(delay) = delay {
+        tokio::time::sleep(delay).await;
+    }
+    Json(json!({ "ID": match_id })).into_response()
+}
+
+async fn select_agent(
+    State((state, _)): State<AppState>,
+    headers: HeaderMap,
+    Path((match_id, agent_id)): Path<(String, String)>,
+) -> Response {
+    let mut state = state.lock();
+    if let Err(status) = check_remote_auth(&state, &headers) {
+        return status.into_response();
+    }
+    if state.loop_state != GameLoopState::Pregame
+        || state.match_id.as_ref() != Some(&match_id)
+    {
+        return riot_error(StatusCode::NOT_FOUND, "MATCH_NOT_FOUND");
+    }
+    if state.locked_agent.is_some() {
+        return riot_error(StatusCode::CONFLICT, "ALREADY_LOCKED");
+    }
+    state.hovered_agent = Some(agent_id);
+    Json(json!({ "ID": match_id })).into_response()
+}
+
+async fn quit_pregame(
+    State((state, _)): State<AppState>,
+    headers: HeaderMap,
+    Path(match_id): Path<String>,
+) -> Response {
+    let state = state.lock();
+    if let Err(status) = check_remote_auth(&state, &headers) {
+        return status.into_response();
+    }
+    if state.match_id.as_ref() != Some(&match_id) {
+        return riot_error(StatusCode::NOT_FOUND, "MATCH_NOT_FOUND");
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn ingame_player(
+    State((state, _)): State<AppState>,
+    headers: HeaderMap,
+    Path(_subject): Path<String>,
+) -> Response {
+    let state = state.lock();
+    if let Err(status) = check_remote_auth(&state, &headers) {
+        return status.into_response();
+    }
+    match (&state.loop_state, &state.match_id) {
+        (GameLoopState::Ingame, Some(match_id)) => Json(json!({
+            "Subject": SUBJECT,
+            "MatchID": match_id,
+            "Version": 1,
+        }))
+        .into_response(),
+        _ => riot_error(StatusCode::NOT_FOUND, "RESOURCE_NOT_FOUND"),
+    }
+}
+
+async fn quit_ingame(
+    State((state, _)): State<AppState>,
+    headers: HeaderMap,
+    Path((_subject, match_id)): Path<(String, String)>,
+) -> Response {
+    let state = state.lock();
+    if let Err(status) = check_remote_auth(&state, &headers) {
+        return status.into_response();
+    }
+    if state.match_id.as_ref() != Some(&match_id) {
+        return riot_error(StatusCode::NOT_FOUND, "MATCH_NOT_FOUND");
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
+
+async fn api_version() -> Json<Value> {
+    Json(json!({
+        "status": 200,
+        "data": {
+            "manifestId": "4223B9537F74423A",
+            "branch": "release-08.05",
+            "version": "08.05.00.2367061",
+            "buildVersion": "9",
+            "engineVersion": "4.27.2.0",
+            "riotClientVersion": "release-08.05-shipping-9-2367061",
+            "riotClientBuild": "82.0.3.1237.2870",
+            "buildDate": "2024-03-15T00:00:00Z",
+        },
+    }))
+}
+
+async fn api_agents() -> Json<Value> {
+    Json(json!({
+        "status": 200,
+        "data": AGENTS
+            .iter()
+            .map(|(uuid, name)| json!({
+                "uuid": uuid,
+                "displayName": name,
+                "isPlayableCharacter": true,
+            }))
+            .collect::<Vec<_>>(),
+    }))
+}
+
+async fn api_maps() -> Json<Value> {
+    Json(json!({
+        "status": 200,
+        "data": MAPS
+            .iter()
+            .map(|(uuid, name, map_url)| json!({
+                "uuid": uuid,
+                "displayName": name,
+                "mapUrl": map_url,
+            }))
+            .collect::<Vec<_>>(),
+    }))
+}
@@ -0,0 +1,197 @@
+//! Whether instalock runs at all, switched by the user from the menu, a key,
+//! the command line or the control api.
+//!
+//! Unlike [`crate::INTERRUPT`], which is only set while a prompt is open, the
+//! pause stays until it is resumed or runs out. Manual locks still work while
+//! paused.
+use std::{fmt::Display, sync::Mutex, time::Duration};
+
+use chrono::{DateTime, Local};
+use dialoguer::console::style;
+use serde::{Deserialize, Serialize};
+
+use crate::events::{self, LifecycleEvent};
+
+static PAUSE: Mutex<Pause> = Mutex::new(Pause::Running);
+
+/// Longest timed pause, a day
+pub const MAX_PAUSE_MINUTES: u64 = 24 * 60;
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Pause {
+    #[default]
+    Running,
+    /// Until resumed
+    Paused,
+    /// Resumes after skipping this many pregames
+    SkipPregames { remaining: u32 },
+    /// Resumes once the time has passed
+    Until { until: DateTime<Local> },
+}
+
+impl Pause {
+    /// `None` for more than [`MAX_PAUSE_MINUTES`]
+    pub fn for_minutes(minutes: u64) -> Option<Self> {
+        if minutes > MAX_PAUSE_MINUTES {
+            return None;
+        }
+        let duration = Duration::from_secs(minutes.checked_mul(60)?);
+        let until = Local::now()
+            .checked_add_signed(chrono::Duration::from_std(duration).ok()?)?;
+        Some(Pause::Until { until })
+    }
+
+    pub fn is_running(&self) -> bool {
+        *self == Pause::Running
+    }
+
+    /// [`Pause::Running`] once a timed pause has run out
+    fn at(self, now: DateTime<Local>) -> Self {
+        match self {
+            Pause::Until { until } if until <= now => Pause::Running,
+            pause => pause,
+        }
+    }
+
+    /// Whether a pregame starting at `now` is skipped, counting it towards
+    /// [`Pause::SkipPregames`]
+    fn skip(&mut self, now: DateTime<Local>) -> bool {
+        *self = self.at(now);
+        match self {
+            Pause::Running => false,
+            Pause::SkipPregames { remaining } if *remaining <= 1 => {
+                *self = Pause::Running;
+                true
+            }
+            Pause::SkipPregames { remaining } => {
+                *remaining -= 1;
+                true
+            }
+            Pause::Paused | Pause::Until { .. } => true,
+        }
+    }
+}
+
+impl Display for Pause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Pause::Running => write!(f, "enabled"),
+            Pause::Paused => write!(f, "paused"),
+            Pause::SkipPregames { remaining: 1 } => {
+                write!(f, "paused for the next pregame")
+            }
+            Pause::SkipPregames { remaining } => {
+                write!(f, "paused for the next {remaining} pregames")
+            }
+            Pause::Until { until } => {
+                write!(f, "paused until {}", until.format("%H:%M"))
+            }
+        }
+    }
+}
+
+/// The pause now, timed pauses that ran out are resumed
+pub fn current() -> Pause {
+    let mut pause = PAUSE.lock().unwrap();
+    let now = pause.at(Local::now());
+    if now != *pause {
+        *pause = now;
+        drop(pause);
+        report(now);
+    }
+    now
+}
+
+pub fn set(pause: Pause) {
+    let pause = match pause {
+        Pause::SkipPregames { remaining: 0 } => Pause::Running,
+        pause => pause,
+    };
+    let prev = std::mem::replace(&mut *PAUSE.lock().unwrap(), pause);
+    if prev != pause {
+        report(pause);
+    }
+}
+
+/// Pauses until resumed, or resumes any kind of pause
+pub fn toggle() {
+    match current() {
+        Pause::Running => set(Pause::Paused),
+        _ => set(Pause::Running),
+    }
+}
+
+/// The pause that skips the pregame starting now, if any
+pub fn skip_pregame() -> Option<Pause> {
+    let mut pause = PAUSE.lock().unwrap();
+    let prev = *pause;
+    if !pause.skip(Local::now()) {
+        return None;
+    }
+    let now = *pause;
+    drop(pause);
+    if now.is_running() {
+        report(now);
+    }
+    Some(prev)
+}
+
+fn report(pause: Pause) {
+    let now = Local::now().format("%H:%M:%S");
+    match pause {
+        Pause::Running => status_line!("{now} - Instalock resumed"),
+        pause => status_line!(
+            "{now} - {}",
+            style(format!("Instalock {pause}")).yellow()
+        ),
+    }
+    log::info!("Instalock {pause}");
+    events::emit(LifecycleEvent::PauseChanged { pause });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_skip_pregames() {
+        let now = Local::now();
+        let mut pause = Pause::SkipPregames { remaining: 2 };
+        assert!(pause.skip(now));
+        assert_eq!(pause, Pause::SkipPregames { remaining: 1 });
+        assert_eq!(pause.to_string(), "paused for the next pregame");
+        assert!(pause.skip(now));
+        assert_eq!(pause, Pause::Running);
+        assert!(!pause.skip(now));
+
+        let mut pause = Pause::Paused;
+        assert!(pause.skip(now));
+        assert!(pause.skip(now));
+        assert_eq!(pause, Pause::Paused);
+    }
+
+    #[test]
+    fn test_timed_pause() {
+        let now = Local::now();
+        let until = now + Duration::from_secs(30 * 60);
+        let mut pause = Pause::Until { until };
+        assert!(pause.skip(now));
+        assert_eq!(pause.at(now), Pause::Until { until });
+        assert_eq!(pause.at(until), Pause::Running);
+        assert!(!pause.skip(until + Duration::from_secs(1)));
+        assert_eq!(pause, Pause::Running);
+        assert!(Pause::for_minutes(MAX_PAUSE_MINUTES).is_some());
+        assert_eq!(Pause::for_minutes(MAX_PAUSE_MINUTES + 1), None);
+        assert_eq!(Pause::for_minutes(u64::MAX), None);
+
+        let json = serde_json::to_value(Pause::Until { until }).unwrap();
+        assert_eq!(json["mode"], "until");
+        assert_eq!(
+            serde_json::to_value(Pause::SkipPregames { remaining: 3 }).unwrap(),
+            serde_json::json!({ "mode": "skip_pregames", "remaining": 3 })
+        );
+    }
+}
@@ -0,0 +1,648 @@
+//! Full-screen dashboard for interactive consoles, replacing the plain
+//! console output and the key menu with panes and the bound keys.
+//!
+//! Console output of the rest of the application goes through
+//! [`status_line`], which moves it to the log pane while the dashboard is
+//! shown. Prompts suspend the dashboard until they are answered.
+use std::{
+    collections::VecDeque,
+    io::{self, Stdout},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Once,
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use crossterm::{
+    event::{
+        Event, EventStream, KeyCode, KeyEvent, KeyEventKind, KeyModifiers,
+    },
+    execute,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use futures::StreamExt;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Row, Table, Wrap},
+    Frame, Terminal,
+};
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+
+use crate::{
+    config::{self, Config, Queue},
+    events::{self, LifecycleEvent},
+    keybindings::{KeyAction, Keybindings},
+    logging::{self, TailLine},
+    pause,
+    valorant_client::{
+        http::CharacterSelectionState, ClientSnapshot, ValorantClientHandle,
+    },
+    CONFIG,
+};
+
+/// Set while the dashboard owns the terminal
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Instalocks kept in the history pane
+const HISTORY_LEN: usize = 20;
+/// Redraw interval, the client state is polled rather than pushed
+const REFRESH: Duration = Duration::from_millis(250);
+
+type SharedClient = Arc<Mutex<Option<ValorantClientHandle>>>;
+
+/// Prints to stderr, or to the log pane while the dashboard is shown. Use
+/// the `status_line!` macro instead of calling this directly.
+pub fn status_line(line: String) {
+    if !ACTIVE.load(Ordering::Relaxed) {
+        eprintln!("{line}");
+        return;
+    }
+    for line in console::strip_ansi_codes(&line).lines() {
+        if !line.trim().is_empty() {
+            logging::push_tail(None, line.to_string());
+        }
+    }
+}
+
+/// The configured binding of the key, ctrl+c always quits since raw mode
+/// swallows the signal
+fn key_action(key: &KeyEvent, keybindings: &Keybindings) -> Option<KeyAction> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+    match keybindings.action(key) {
+        None if key.code == KeyCode::Char('c')
+            && key.modifiers == KeyModifiers::CONTROL =>
+        {
+            Some(KeyAction::Quit)
+        }
+        action => action,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HistoryEntry {
+    time: DateTime<Local>,
+    map: Option<String>,
+    agent: String,
+    result: String,
+    success: bool,
+}
+
+/// State collected from lifecycle events, everything else is read when
+/// drawing
+#[derive(Debug, Default)]
+struct Dashboard {
+    lockfile_found: bool,
+    /// Region and shard of the initialized client
+    client: Option<(String, String)>,
+    /// Map of the current or last pregame, for the history
+    map_name: Option<String>,
+    /// Newest first
+    history: VecDeque<HistoryEntry>,
+}
+
+impl Dashboard {
+    fn handle_event(&mut self, time: DateTime<Local>, event: &LifecycleEvent) {
+        let (agent, result, success) = match event {
+            LifecycleEvent::LockfileCreated => {
+                self.lockfile_found = true;
+                return;
+            }
+            LifecycleEvent::LockfileDeleted => {
+                self.lockfile_found = false;
+                self.client = None;
+                return;
+            }
+            LifecycleEvent::ClientInitialized { region, shard } => {
+                self.lockfile_found = true;
+                self.client = Some((region.clone(), shard.clone()));
+                return;
+            }
+            LifecycleEvent::PregameEntered { map_name, .. } => {
+                self.map_name = Some(map_name.clone());
+                return;
+            }
+            LifecycleEvent::LockSucceeded {
+                agent_name,
+                state,
+                latency_ms,
+                ..
+            } => (
+                agent_name.clone(),
+                format!("{} in {latency_ms}ms", state_name(*state)),
+                true,
+            ),
+            LifecycleEvent::LockFailed {
+                agent_name,
+                latency_ms,
+                error,
+                ..
+            } => (
+                agent_name.clone(),
+                format!("Failed after {latency_ms}ms: {error}"),
+                false,
+            ),
+            LifecycleEvent::AllAgentsFailed { attempts, .. } => (
+                "-".to_string(),
+                format!("All {attempts} agents failed"),
+                false,
+            ),
+            _ => return,
+        };
+        if self.history.len() == HISTORY_LEN {
+            self.history.pop_back();
+        }
+        self.history.push_front(HistoryEntry {
+            time,
+            map: self.map_name.clone(),
+            agent,
+            result,
+            success,
+        });
+    }
+
+    fn render(
+        &self,
+        frame: &mut Frame,
+        client: Option<&ClientSnapshot>,
+        cfg: &Config,
+    ) {
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(6),
+                Constraint::Length(9),
+                Constraint::Min(3),
+                // bindings wrap on narrow consoles
+                Constraint::Length(2),
+            ])
+            .split(frame.size());
+        let top = halves(rows[0]);
+        let middle = halves(rows[1]);
+        frame.render_widget(
+            Paragraph::new(self.connection_lines(client))
+                .block(pane("Connection")),
+            top[0],
+        );
+        frame.render_widget(
+            Paragraph::new(game_lines(client)).block(pane("Game")),
+            top[1],
+        );
+        frame.render_widget(
+            Paragraph::new(config_lines(client, cfg)).block(pane("Config")),
+            middle[0],
+        );
+        frame.render_widget(self.history_table(), middle[1]);
+        let log_lines = logging::tail(rows[2].height.saturating_sub(2).into())
+            .iter()
+            .map(log_line)
+            .collect::<Vec<_>>();
+        frame.render_widget(
+            Paragraph::new(log_lines).block(pane("Log")),
+            rows[2],
+        );
+        frame.render_widget(
+            Paragraph::new(hotkey_line(&cfg.keybindings))
+                .wrap(Wrap { trim: true }),
+            rows[3],
+        );
+    }
+
+    fn connection_lines(
+        &self,
+        client: Option<&ClientSnapshot>,
+    ) -> Vec<Line<'static>> {
+        let lockfile = match self.lockfile_found {
+            true => Span::styled("found", Style::new().fg(Color::Green)),
+            false => Span::styled(
+                "waiting for Valorant",
+                Style::new().fg(Color::Yellow),
+            ),
+        };
+        let connection = match (&self.client, client) {
+            (Some((region, shard)), Some(_)) => Span::styled(
+                format!("connected ({region}/{shard})"),
+                Style::new().fg(Color::Green),
+            ),
+            _ => Span::styled("not connected", Style::new().fg(Color::Red)),
+        };
+        let instalock = match pause::current() {
+            pause if pause.is_running() => {
+                Span::styled(pause.to_string(), Style::new().fg(Color::Green))
+            }
+            pause => {
+                Span::styled(pause.to_string(), Style::new().fg(Color::Yellow))
+            }
+        };
+        vec![
+            Line::from(vec![Span::raw("Lockfile:  "), lockfile]),
+            Line::from(vec![Span::raw("Client:    "), connection]),
+            Line::from(format!("Profile:   {}", config::active_profile())),
+            Line::from(vec![Span::raw("Instalock: "), instalock]),
+        ]
+    }
+
+    fn history_table(&self) -> Table<'static> {
+        let rows = self.history.iter().map(|entry| {
+            let style = match entry.success {
+                true => Style::new().fg(Color::Green),
+                false => Style::new().fg(Color::Red),
+            };
+            Row::new(vec![
+                entry.time.format("%H:%M:%S").to_string(),
+                entry.map.clone().unwrap_or_default(),
+                entry.agent.clone(),
+                entry.result.clone(),
+            ])
+            .style(style)
+        });
+        Table::new(
+            rows,
+            [
+                Constraint::Length(8),
+                Constraint::Length(10),
+                Constraint::Length(10),
+                Constraint::Min(10),
+            ],
+        )
+        .header(
+            Row::new(["Time", "Map", "Agent", "Result"])
+                .style(Style::new().add_modifier(Modifier::BOLD)),
+        )
+        .block(pane("Recent instalocks"))
+    }
+}
+
+fn halves(area: Rect) -> std::rc::Rc<[Rect]> {
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(area)
+}
+
+fn pane(title: &'static str) -> Block<'static> {
+    Block::default().borders(Borders::ALL).title(title)
+}
+
+fn state_name(state: CharacterSelectionState) -> &'static str {
+    match state {
+        CharacterSelectionState::Selected => "Hovered",
+        CharacterSelectionState::Locked => "Locked",
+        CharacterSelectionState::None => "None",
+        CharacterSelectionState::Unknown => "Unknown",
+    }
+}
+
+fn game_lines(client: Option<&ClientSnapshot>) -> Vec<Line<'static>> {
+    let Some(client) = client else {
+        return vec![Line::from("Not connected")];
+    };
+    let map = match (&client.map, &client.queue) {
+        (Some(map), queue) => {
+            let queue = match queue.as_deref().map(Queue::from_id) {
+                Some(Some(known)) => format!(" ({known})"),
+                Some(None) => format!(" ({})", queue.as_deref().unwrap()),
+                None => String::new(),
+            };
+            format!("{}{queue}", config::map_name(map))
+        }
+        (None, _) => "-".to_string(),
+    };
+    let agent = match (&client.agent, client.agent_state) {
+        (Some(agent), Some(state)) => format!(
+            "{} ({})",
+            config::agent_name(agent),
+            state_name(state).to_lowercase()
+        ),
+        _ => "-".to_string(),
+    };
+    vec![
+        Line::from(format!("State: {:?}", client.loop_state)),
+        Line::from(format!(
+            "Match: {}",
+            client.match_id.as_deref().unwrap_or("-")
+        )),
+        Line::from(format!("Map:   {map}")),
+        Line::from(format!("Agent: {agent}")),
+    ]
+}
+
+/// The agents for the current map, or the whole agent config outside of
+/// pregame
+fn config_lines(
+    client: Option<&ClientSnapshot>,
+    cfg: &Config,
+) -> Vec<Line<'static>> {
+    let mut lines = vec![
+        Line::from(format!("Lock strategy: {}", cfg.lock_strategy)),
+        Line::from(format!("Lock timing: {}", cfg.lock_timing)),
+        Line::from(format!("Initial delay: {}ms", cfg.instalock_wait_ms)),
+        Line::from(format!(
+            "Skip hovered agents: {}",
+            if cfg.skip_hovered_agents { "yes" } else { "no" }
+        )),
+    ];
+    match client.and_then(|c| Some((c.map.as_deref()?, c.queue.as_deref()))) {
+        Some((map, queue)) => {
+            let agents = cfg
+                .map_agent_config_for(queue)
+                .agent_config(map)
+                .map_or("Disabled".to_string(), |agents| agents.to_string());
+            lines.push(Line::from(format!(
+                "Agents on {}: {agents}",
+                config::map_name(map)
+            )));
+        }
+        None => lines.extend(
+            cfg.describe_agent_config()
+                .lines()
+                .map(|line| Line::from(line.to_string())),
+        ),
+    }
+    lines
+}
+
+fn log_line(line: &TailLine) -> Line<'static> {
+    let time = Span::styled(
+        line.time.format("%H:%M:%S ").to_string(),
+        Style::new().fg(Color::DarkGray),
+    );
+    let Some(level) = line.level else {
+        return Line::from(vec![time, Span::raw(line.message.clone())]);
+    };
+    let color = match level {
+        log::Level::Error => Color::Red,
+        log::Level::Warn => Color::Yellow,
+        log::Level::Info => Color::Cyan,
+        _ => Color::DarkGray,
+    };
+    Line::from(vec![
+        time,
+        Span::styled(format!("{level:<5} "), Style::new().fg(color)),
+        Span::raw(line.message.clone()),
+    ])
+}
+
+fn hotkey_line(keybindings: &Keybindings) -> Line<'static> {
+    let mut spans = Vec::new();
+    for (key, label) in keybindings.by_action() {
+        spans.push(Span::styled(
+            format!(" {key} "),
+            Style::new().add_modifier(Modifier::REVERSED),
+        ));
+        spans.push(Span::raw(format!(" {label}  ")));
+    }
+    Line::from(spans)
+}
+
+/// Takes over the terminal and shows the dashboard, the task returns `true`
+/// when the user quits
+pub fn spawn(client: SharedClient) -> anyhow::Result<JoinHandle<bool>> {
+    enter()?;
+    let terminal = match Terminal::new(CrosstermBackend::new(io::stdout())) {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            restore();
+            return Err(err.into());
+        }
+    };
+    static PANIC_HOOK: Once = Once::new();
+    PANIC_HOOK.call_once(|| {
+        let prev = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            restore();
+            prev(info);
+        }));
+    });
+    Ok(tokio::spawn(async move {
+        let _guard = RestoreOnDrop;
+        match run(terminal, client).await {
+            Ok(quit) => quit,
+            Err(err) => {
+                log::error!("Dashboard stopped: {err:#}");
+                false
+            }
+        }
+    }))
+}
+
+fn enter() -> io::Result<()> {
+    enable_raw_mode()?;
+    if let Err(err) = execute!(io::stdout(), EnterAlternateScreen) {
+        let _ = disable_raw_mode();
+        return Err(err);
+    }
+    ACTIVE.store(true, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Gives the terminal back, does nothing if the dashboard is not shown
+pub fn restore() {
+    if ACTIVE.swap(false, Ordering::Relaxed) {
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+    }
+}
+
+struct RestoreOnDrop;
+
+impl Drop for RestoreOnDrop {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// `Ok(true)` when the user quits
+async fn run(
+    mut terminal: Terminal<CrosstermBackend<Stdout>>,
+    client: SharedClient,
+) -> anyhow::Result<bool> {
+    let mut dashboard = Dashboard::default();
+    let mut events = events::subscribe();
+    let mut keys = EventStream::new();
+    let mut refresh = tokio::time::interval(REFRESH);
+    loop {
+        if !ACTIVE.load(Ordering::Relaxed) {
+            return Ok(false);
+        }
+        let snapshot = client
+            .lock()
+            .unwrap()
+            .as_ref()
+            .and_then(ValorantClientHandle::status);
+        let cfg = CONFIG.borrow().clone();
+        terminal
+            .draw(|frame| dashboard.render(frame, snapshot.as_ref(), &cfg))?;
+        let action = tokio::select! {
+            _ = refresh.tick() => None,
+            event = events.recv() => {
+                match event {
+                    Ok(event) => dashboard.handle_event(event.time, &event.event),
+                    Err(RecvError::Lagged(_)) => (),
+                    Err(RecvError::Closed) => return Ok(false),
+                }
+                None
+            }
+            key = keys.next() => match key {
+                Some(Ok(Event::Key(key))) => key_action(&key, &cfg.keybindings),
+                Some(Ok(_)) => None,
+                Some(Err(err)) => {
+                    log::warn!("Error in console event stream: {err}");
+                    None
+                }
+                None => return Ok(false),
+            },
+        };
+        match action {
+            None => (),
+            Some(KeyAction::Quit) => {
+                log::info!("Quit from dashboard");
+                return Ok(true);
+            }
+            Some(action) if action.prompts() => {
+                drop(keys);
+                suspend(&mut terminal, crate::run_key_action(action, &client))
+                    .await?;
+                keys = EventStream::new();
+            }
+            Some(action) => crate::run_key_action(action, &client).await,
+        }
+    }
+}
+
+/// Hands the terminal to the prompts in `f`
+async fn suspend(
+    terminal: &mut Terminal<CrosstermBackend<Stdout>>,
+    f: impl std::future::Future<Output = ()>,
+) -> anyhow::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    ACTIVE.store(false, Ordering::Relaxed);
+    f.await;
+    ACTIVE.store(true, Ordering::Relaxed);
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    terminal.clear()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use ratatui::backend::TestBackend;
+
+    use crate::valorant_client::types::GameLoopState;
+
+    use super::*;
+
+    fn screen(terminal: &Terminal<TestBackend>) -> String {
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[test]
+    fn test_history_from_events() {
+        let mut dashboard = Dashboard::default();
+        let now = Local::now();
+        dashboard.handle_event(now, &LifecycleEvent::LockfileCreated);
+        dashboard.handle_event(
+            now,
+            &LifecycleEvent::PregameEntered {
+                match_id: Some("match-1".to_string()),
+                map: "/Game/Maps/Ascent/Ascent".to_string(),
+                map_name: "Ascent".to_string(),
+                queue: None,
+            },
+        );
+        dashboard.handle_event(
+            now,
+            &LifecycleEvent::LockFailed {
+                agent: "jett-id".to_string(),
+                agent_name: "Jett".to_string(),
+                state: CharacterSelectionState::Locked,
+                latency_ms: 30,
+                error: "taken".to_string(),
+                error_code: None,
+            },
+        );
+        dashboard.handle_event(
+            now,
+            &LifecycleEvent::LockSucceeded {
+                agent: "raze-id".to_string(),
+                agent_name: "Raze".to_string(),
+                state: CharacterSelectionState::Locked,
+                latency_ms: 42,
+            },
+        );
+        assert!(dashboard.lockfile_found);
+        let history: Vec<_> = dashboard
+            .history
+            .iter()
+            .map(|e| (e.map.as_deref(), e.agent.as_str(), e.result.as_str()))
+            .collect();
+        assert_eq!(
+            history,
+            vec![
+                (Some("Ascent"), "Raze", "Locked in 42ms"),
+                (Some("Ascent"), "Jett", "Failed after 30ms: taken"),
+            ]
+        );
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let snapshot = ClientSnapshot {
+            loop_state: GameLoopState::Pregame,
+            match_id: Some("match-1".to_string()),
+            map: None,
+            queue: None,
+            agent: None,
+            agent_state: None,
+        };
+        terminal
+            .draw(|frame| {
+                dashboard.render(frame, Some(&snapshot), &Config::default())
+            })
+            .unwrap();
+        let screen = screen(&terminal);
+        assert!(screen.contains("Lockfile:  found"), "{screen}");
+        assert!(screen.contains("State: Pregame"), "{screen}");
+        assert!(screen.contains("Locked in 42ms"), "{screen}");
+        assert!(screen.contains(" ctrl+q  quit"), "{screen}");
+    }
+
+    #[test]
+    fn test_hotkeys() {
+        let key = |code, modifiers| KeyEvent::new(code, modifiers);
+        let bindings = Keybindings::default();
+        assert_eq!(
+            key_action(
+                &key(KeyCode::Char('D'), KeyModifiers::CONTROL),
+                &bindings
+            ),
+            Some(KeyAction::QuitPregame)
+        );
+        assert_eq!(
+            key_action(
+                &key(KeyCode::Char('c'), KeyModifiers::CONTROL),
+                &bindings
+            ),
+            Some(KeyAction::Quit)
+        );
+        assert_eq!(
+            key_action(&key(KeyCode::Char('x'), KeyModifiers::NONE), &bindings),
+            None
+        );
+    }
+}
@@ -3,6 +3,8 @@ use std::fmt::Display;
 use anyhow::{bail, Context};
 use serde::{Deserialize, Serialize};
 
+use crate::endpoints::Endpoints;
+
 pub async fn agent_from_name(agent_name: &str) -> anyhow::Result<GameAgent> {
     crate::global::GAME_AGENTS
         .get()
@@ -23,11 +25,42 @@ pub async fn map_from_map_url(map_url: &str) -> anyhow::Result<GameMap> {
         .context(format!("Map '{map_url}' could not be found."))
 }
 
-pub async fn fetch_api_version() -> anyhow::Result<ValorantApiVersion> {
+/// Finds the agent by display name, ignoring case, or uuid
+pub fn find_agent<'a>(
+    agents: &'a [GameAgent],
+    name: &str,
+) -> anyhow::Result<&'a GameAgent> {
+    if agents.is_empty() {
+        bail!("No agents available, failed to reach valorant-api.com");
+    }
+    agents
+        .iter()
+        .find(|a| a.name.0.eq_ignore_ascii_case(name.trim()) || a.uuid == name)
+        .with_context(|| format!("Unknown agent '{name}', see `agents list`"))
+}
+
+/// Finds the map by display name, ignoring case, or map url
+pub fn find_map<'a>(
+    maps: &'a [GameMap],
+    name: &str,
+) -> anyhow::Result<&'a GameMap> {
+    if maps.is_empty() {
+        bail!("No maps available, failed to reach valorant-api.com");
+    }
+    maps.iter()
+        .find(|m| {
+            m.name.0.eq_ignore_ascii_case(name.trim()) || m.map_url.0 == name
+        })
+        .with_context(|| format!("Unknown map '{name}', see `maps list`"))
+}
+
+pub async fn fetch_api_version(
+    endpoints: &Endpoints,
+) -> anyhow::Result<ValorantApiVersion> {
     let api_version_response = reqwest::ClientBuilder::new()
         .timeout(std::time::Duration::from_secs(2))
         .build()?
-        .get("https://valorant-api.com/v1/version")
+        .get(endpoints.valorant_api("v1/version"))
         .send()
         .await?
         .text()
@@ -57,11 +90,13 @@ pub struct GameAgent {
 }
 
 impl GameAgent {
-    pub async fn fetch_all() -> anyhow::Result<Vec<GameAgent>> {
+    pub async fn fetch_all(
+        endpoints: &Endpoints,
+    ) -> anyhow::Result<Vec<GameAgent>> {
         let fetch_all_agents_response = reqwest::ClientBuilder::new()
             .timeout(std::time::Duration::from_secs(2))
             .build()?
-            .get("https://valorant-api.com/v1/agents")
+            .get(endpoints.valorant_api("v1/agents"))
             .send()
             .await?
             .text()
@@ -99,11 +134,13 @@ pub struct GameMap {
 }
 
 impl GameMap {
-    pub async fn fetch_all() -> anyhow::Result<Vec<GameMap>> {
+    pub async fn fetch_all(
+        endpoints: &Endpoints,
+    ) -> anyhow::Result<Vec<GameMap>> {
         let fetch_all_maps_response = reqwest::ClientBuilder::new()
             .timeout(std::time::Duration::from_secs(2))
             .build()?
-            .get("https://valorant-api.com/v1/maps")
+            .get(endpoints.valorant_api("v1/maps"))
             .send()
             .await?
             .text()
@@ -2,6 +2,7 @@ use anyhow::Context;
 use parking_lot::lock_api::ArcMutexGuard;
 use parking_lot::{Mutex, RawMutex};
 use reqwest::Client;
+use serde::Serialize;
 use std::ops::Deref;
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
@@ -9,33 +10,73 @@ use std::time::Duration;
 
 use tokio::sync::mpsc::Receiver;
 use tokio::sync::mpsc::{channel, Sender};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
 use tokio::time::sleep_until;
 use tokio::time::Instant;
 
+use self::error::{ApiResult, ValorantApiError};
 use self::stream::ValorantEventStream;
 use self::types::ValorantClientAuth;
+use self::warmup::ConnectionWarmth;
+use crate::config::{
+    self, AgentId, LockStrategy, LockTiming, MapId, Queue, QueueId,
+};
+use crate::endpoints::Endpoints;
+use crate::events::{self, LifecycleEvent};
 use crate::global::{API_VERSION, GAME_MAPS};
+use crate::valo_types::GameAgent;
+use crate::valorant_client::http::CharacterSelectionState;
+use crate::valorant_client::http::PregameMatch;
 use crate::valorant_client::http::ProductId;
 use crate::valorant_client::types::ClientStatus;
 use crate::valorant_client::types::GameLoopState;
-use crate::INTERRUPT;
 use crate::{
     config::Config, lockfile::Lockfile, valorant_client::stream::ValorantEvent,
 };
+use crate::{pause, INTERRUPT};
 
-mod http;
+pub mod error;
+pub mod http;
+pub mod retry;
 mod stream;
-mod types;
+pub mod types;
+mod warmup;
+
+/// How often the pregame is fetched while waiting for teammates, see
+/// [`LockTiming::AsLateAsPossible`]
+const TEAMMATES_POLL_INTERVAL: Duration = Duration::from_secs(1);
 
 pub enum ValorantCommand {
     QuitPregame,
     QuitGame,
+    LockAgent(AgentId),
+    /// Hovers the agent without locking it
+    SelectAgent(AgentId),
+    /// Locks the configured agent after the one picked last
+    LockNextAgent,
+    /// Locks the first available agent of the configured ones
+    RetryFallback,
 }
 
 /// Drop to stop the client
 #[derive(Debug, Clone)]
 pub struct ValorantClientHandle {
     tx: Sender<ValorantCommand>,
+    client_state: Arc<Mutex<MaybeValorantClient>>,
+}
+
+/// What the client is currently doing, see [`ValorantClient::status`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ClientSnapshot {
+    pub loop_state: GameLoopState,
+    pub match_id: Option<String>,
+    /// Only known in pregame
+    pub map: Option<MapId>,
+    pub queue: Option<QueueId>,
+    /// The agent we hovered or locked in the current pregame
+    pub agent: Option<AgentId>,
+    pub agent_state: Option<CharacterSelectionState>,
 }
 
 #[derive(Debug, Clone)]
@@ -44,13 +85,17 @@ pub struct ShardRegion {
     region: String,
 }
 
+#[derive(Debug)]
 pub enum MaybeValorantClient {
     Client(ValorantClient),
-    Parts(Lockfile, Config),
+    Parts(Lockfile, watch::Receiver<Config>),
 }
 
 impl MaybeValorantClient {
-    pub async fn init(lockfile: Lockfile, config: Config) -> Self {
+    pub async fn init(
+        lockfile: Lockfile,
+        config: watch::Receiver<Config>,
+    ) -> Self {
         match ValorantClient::init(lockfile.clone(), config.clone()).await {
             Ok(client) => Self::Client(client),
             Err(err) => {
@@ -87,42 +132,56 @@ impl MaybeValorantClient {
 pub struct ValorantClient {
     client: Client,
     pub running: Arc<AtomicBool>,
-    pub config: Config,
+    /// Read once per pregame, so config changes apply to the next one
+    pub config: watch::Receiver<Config>,
     pub lockfile: Lockfile,
     pub shard: String,
     pub region: String,
     pub subject: String,
     pub version: String,
     pub platform: String,
+    pub endpoints: Endpoints,
     auth: Arc<Mutex<ValorantClientAuth>>,
     current_match_id: Arc<Mutex<Option<String>>>,
+    /// Last fetched pregame match, only set while in pregame
+    pregame_match: Arc<Mutex<Option<PregameMatch>>>,
     loop_state: Arc<Mutex<GameLoopState>>,
+    /// Agent we hovered or locked in the current pregame
+    selected_agent: Arc<Mutex<Option<(AgentId, CharacterSelectionState)>>>,
+    warmth: Arc<Mutex<ConnectionWarmth>>,
+    /// Instalock for the current pregame, see [`Self::spawn_handle_pregame`]
+    pregame_task: Arc<Mutex<Option<JoinHandle<()>>>>,
 }
 
 impl ValorantClient {
     pub async fn init(
         lockfile: Lockfile,
-        config: Config,
+        config: watch::Receiver<Config>,
     ) -> anyhow::Result<Self> {
+        // timeouts are set per attempt by the retry policies
         let client = reqwest::Client::builder()
             .danger_accept_invalid_certs(true)
-            .timeout(std::time::Duration::from_millis(1500))
             .build()
             .unwrap();
-        let auth = Self::fetch_auth_tokens(&client, &lockfile).await?;
-        let session = Self::sessions_info(&client, &lockfile)
-            .await?
-            .into_iter()
-            .find_map(|(_, session)| {
-                if session.product_id == ProductId::Valorant {
-                    Some(session)
-                } else {
-                    None
-                }
-            })
-            .context(
-                "No Valorant session returned by local sessions endpoint",
-            )?;
+        let endpoints = Endpoints::resolve(&config.borrow().endpoints);
+        let policy = config.borrow().retry.default.clone();
+        let auth =
+            Self::fetch_auth_tokens(&client, &endpoints, &lockfile, &policy)
+                .await?;
+        let session =
+            Self::sessions_info(&client, &endpoints, &lockfile, &policy)
+                .await?
+                .into_iter()
+                .find_map(|(_, session)| {
+                    if session.product_id == ProductId::Valorant {
+                        Some(session)
+                    } else {
+                        None
+                    }
+                })
+                .context(
+                    "No Valorant session returned by local sessions endpoint",
+                )?;
         let region = session
             .launch_configuration
             .region()
@@ -139,21 +198,26 @@ impl ValorantClient {
         // lock agent
         let this = Self::new(
             client, subject, config, region, shard, version, platform, auth,
-            lockfile,
+            lockfile, endpoints,
         );
+        events::emit(LifecycleEvent::ClientInitialized {
+            region: this.region.clone(),
+            shard: this.shard.clone(),
+        });
+        this.spawn_keep_warm();
         match this.current_pregame().await {
             Ok(pregame) => {
-                this.set_loop_state(GameLoopState::Pregame);
                 let _ = this.current_match_id.lock().replace(pregame.match_id);
-                let _ = this.handle_pregame(false).await;
+                this.set_loop_state(GameLoopState::Pregame);
+                this.spawn_handle_pregame(false);
             }
             Err(err) => {
                 log::error!("Failed to fetch pregame match: {}", err);
                 let ingame = this.current_ingame().await;
                 if let Ok(ingame) = ingame {
-                    this.set_loop_state(GameLoopState::Ingame);
                     log::info!("In Game: {}", ingame.match_id);
                     *this.current_match_id() = Some(ingame.match_id);
+                    this.set_loop_state(GameLoopState::Ingame);
                 }
             }
         }
@@ -163,17 +227,19 @@ impl ValorantClient {
     pub fn new(
         client: Client,
         subject: String,
-        config: Config,
+        config: watch::Receiver<Config>,
         region: String,
         shard: String,
         version: String,
         platform: String,
         auth: ValorantClientAuth,
         lockfile: Lockfile,
+        endpoints: Endpoints,
     ) -> Self {
         Self {
             client,
             config,
+            endpoints,
             running: Arc::new(AtomicBool::new(true)),
             auth: Arc::new(Mutex::new(auth)),
             region,
@@ -183,7 +249,11 @@ impl ValorantClient {
             platform,
             lockfile,
             current_match_id: Arc::new(Mutex::new(None)),
+            pregame_match: Arc::new(Mutex::new(None)),
             loop_state: Arc::new(Mutex::new(GameLoopState::Menus)),
+            selected_agent: Arc::new(Mutex::new(None)),
+            warmth: Arc::new(Mutex::new(ConnectionWarmth::default())),
+            pregame_task: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -195,19 +265,104 @@ impl ValorantClient {
         self.current_match_id.lock_arc()
     }
 
+    pub fn pregame_match(
+        &self,
+    ) -> ArcMutexGuard<RawMutex, Option<PregameMatch>> {
+        self.pregame_match.lock_arc()
+    }
+
     pub fn loop_state(&self) -> GameLoopState {
         *self.loop_state.lock()
     }
 
+    /// Set the match id first, it is part of the emitted event
     pub fn set_loop_state(&self, loop_state: GameLoopState) {
         *self.loop_state.lock() = loop_state;
+        if let Some(task) = self.pregame_task.lock().take() {
+            task.abort();
+        }
+        *self.selected_agent.lock() = None;
+        events::emit(LifecycleEvent::LoopStateChanged {
+            loop_state,
+            match_id: self.current_match_id().clone(),
+        });
+    }
+
+    pub fn status(&self) -> ClientSnapshot {
+        let pregame = self.pregame_match().clone();
+        let selected = self.selected_agent.lock().clone();
+        ClientSnapshot {
+            loop_state: self.loop_state(),
+            match_id: self.current_match_id().clone(),
+            map: pregame.as_ref().map(|p| p.map_url.clone()),
+            queue: pregame.as_ref().map(|p| p.queue().to_string()),
+            agent: selected.as_ref().map(|(agent, _)| agent.clone()),
+            agent_state: selected.map(|(_, state)| state),
+        }
+    }
+
+    /// Locks or hovers the agent and remembers it for [`Self::status`]
+    async fn pick_agent(
+        &self,
+        agent_id: &str,
+        state: CharacterSelectionState,
+    ) -> ApiResult<()> {
+        let agent = agent_id.to_string();
+        let agent_name = config::agent_name(agent_id);
+        events::emit(LifecycleEvent::LockAttempt {
+            agent: agent.clone(),
+            agent_name: agent_name.clone(),
+            state,
+        });
+        let begin = Instant::now();
+        let res = match state {
+            CharacterSelectionState::Selected => {
+                self.select_agent(agent_id).await
+            }
+            _ => self.lock_agent(agent_id).await,
+        };
+        let latency_ms = begin.elapsed().as_millis() as u64;
+        match &res {
+            Ok(()) => {
+                *self.selected_agent.lock() = Some((agent.clone(), state));
+                events::emit(LifecycleEvent::LockSucceeded {
+                    agent,
+                    agent_name,
+                    state,
+                    latency_ms,
+                });
+            }
+            Err(err) => events::emit(LifecycleEvent::LockFailed {
+                agent,
+                agent_name,
+                state,
+                latency_ms,
+                error: format!("{err:#}"),
+                error_code: err
+                    .riot_error()
+                    .map(|body| body.error_code.clone())
+                    .filter(|code| !code.is_empty()),
+            }),
+        }
+        res
+    }
+
+    /// Runs [`Self::handle_pregame`] in the background, so long waits for
+    /// the phase timer don't hold up other events. It is aborted on the next
+    /// loop state change.
+    fn spawn_handle_pregame(&self, wait: bool) {
+        let this = self.clone();
+        let task = tokio::spawn(async move {
+            let _ = this.handle_pregame(wait).await;
+        });
+        if let Some(prev) = self.pregame_task.lock().replace(task) {
+            prev.abort();
+        }
     }
 
     async fn handle_pregame(&self, wait: bool) -> Option<()> {
         let begin_event = Instant::now();
-        let instalock_wait = sleep_until(
-            begin_event + Duration::from_millis(self.config.instalock_wait_ms),
-        );
+        let config = self.config.borrow().clone();
         log::info!(
             "handle pregame (Pregame started): {}",
             self.current_match_id().deref().as_ref()?
@@ -216,47 +371,222 @@ impl ValorantClient {
             log::info!("Interrupted.");
             return None;
         }
-        let map = match self.get_pregame_match().await {
-            Ok(pregame) => GAME_MAPS
-                .get()
-                .unwrap()
-                .iter()
-                .find(|map| map.map_url.0 == pregame.map_url)
-                .unwrap(),
+        if let Some(pause) = pause::skip_pregame() {
+            let now = chrono::Local::now();
+            status_line!(
+                "{} - Instalock {pause}, not locking",
+                now.format("%H:%M:%S")
+            );
+            log::info!("Instalock {pause}, skipping pregame");
+            return None;
+        }
+        let fetch_begin = Instant::now();
+        let pregame = match self.get_pregame_match().await {
+            Ok(pregame) => Some(pregame),
+            Err(err @ ValorantApiError::NotFound(_)) => {
+                log::warn!("Pregame is already over: {err}");
+                return None;
+            }
             Err(err) => {
-                eprintln!("Failed to fetch pregame match map: {}", err);
-                eprintln!("Proceeding with Ascent as map.");
+                status_line!("Failed to fetch pregame match map: {}", err);
+                status_line!("Proceeding with Ascent as map.");
 
                 log::error!("Failed to fetch pregame match map: {}", err);
-                GAME_MAPS
-                    .get()
-                    .unwrap()
-                    .iter()
-                    .find(|map| map.name.0 == "Ascent")
-                    .unwrap()
+                None
+            }
+        };
+        let map_url = match &pregame {
+            Some(pregame) => pregame.map_url.clone(),
+            None => "/Game/Maps/Ascent/Ascent".to_string(),
+        };
+        let map_name = match GAME_MAPS
+            .get()
+            .unwrap()
+            .iter()
+            .find(|map| map.map_url.0 == map_url)
+        {
+            Some(map) => map.name.0.clone(),
+            // e.g. a new map missing in a stale cache, only the default
+            // agents can apply
+            None => {
+                log::warn!("Unknown map {map_url}, using the default agents");
+                map_url.rsplit('/').next().unwrap_or_default().to_string()
             }
         };
+        *self.pregame_match() = pregame.clone();
+        let queue = pregame.as_ref().map(|p| p.queue().to_string());
+        events::emit(LifecycleEvent::PregameEntered {
+            match_id: self.current_match_id().clone(),
+            map: map_url.clone(),
+            map_name: map_name.clone(),
+            queue: queue.clone(),
+        });
+        let queue_name = match queue.as_deref().map(Queue::from_id) {
+            Some(Some(known)) => format!(" ({known})"),
+            Some(None) => format!(" ({})", queue.as_deref().unwrap()),
+            None => "".to_string(),
+        };
         let now = chrono::Local::now();
-        eprintln!(
-            "{} - Entered Pregame for {}",
+        status_line!(
+            "{} - Entered Pregame for {}{queue_name}",
             now.format("%H:%M:%S"),
-            console::style(format!("{}", map.name.0)).cyan()
+            console::style(&map_name).cyan()
         );
-        let agents = self.config.get_agents(map.name.0.as_str());
-        let mut i = 0;
-        // initial wait
-        if wait {
-            instalock_wait.await;
+        let agents = config.get_agents(&map_url, queue.as_deref());
+        // the timer was read at some point during the request
+        let phase_end = pregame
+            .as_ref()
+            .map(|pregame| pregame.phase_time_remaining())
+            .filter(|remaining| !remaining.is_zero())
+            .map(|remaining| fetch_begin + remaining);
+        // nothing was chosen to lock for maps without agents
+        let safety_lock_at = phase_end
+            .zip(config.safety_lock_ms)
+            .filter(|_| !agents.is_empty())
+            .and_then(|(end, ms)| end.checked_sub(Duration::from_millis(ms)));
+        let locking = self.lock_agents(
+            agents.clone(),
+            pregame,
+            &config,
+            begin_event,
+            phase_end,
+            wait,
+        );
+        let locked = match safety_lock_at {
+            Some(safety_lock_at) => tokio::select! {
+                locked = locking => locked?,
+                _ = sleep_until(safety_lock_at) => false,
+            },
+            None => locking.await?,
+        };
+        if let (false, Some(safety_lock_at)) = (locked, safety_lock_at) {
+            sleep_until(safety_lock_at).await;
+            self.safety_lock(&agents, &config).await;
+        }
+        Some(())
+    }
+
+    /// Waits for [`Config::lock_timing`] and locks the first agent that
+    /// works, `Some(true)` if one was locked and `None` if instalock had to
+    /// stop
+    async fn lock_agents(
+        &self,
+        agents: Vec<GameAgent>,
+        mut pregame: Option<PregameMatch>,
+        config: &Config,
+        begin_event: Instant,
+        phase_end: Option<Instant>,
+        wait: bool,
+    ) -> Option<bool> {
+        // the initial delay only counts from the start of the pregame
+        let lock_at = match (config.lock_timing, wait) {
+            (LockTiming::AfterStart, false) => None,
+            (timing, _) => Some(timing.lock_at(
+                config.instalock_wait_ms,
+                begin_event,
+                phase_end,
+            )),
+        };
+        if let Some(lock_at) = lock_at {
+            match config.lock_timing {
+                LockTiming::AsLateAsPossible { .. } => {
+                    self.wait_for_teammates(lock_at).await
+                }
+                _ => sleep_until(lock_at).await,
+            }
             log::info!(
                 "Instalock wait finished ({}ms)",
-                self.config.instalock_wait_ms
+                begin_event.elapsed().as_millis()
             );
+            // teammates might have picked agents in the meantime
+            if lock_at > begin_event && !agents.is_empty() {
+                match self.get_pregame_match().await {
+                    // e.g. manually while waiting for the timer
+                    Ok(refreshed) if refreshed.is_locked(&self.subject) => {
+                        log::info!("Agent already locked, not locking");
+                        *self.pregame_match() = Some(refreshed);
+                        return Some(true);
+                    }
+                    Ok(refreshed) => {
+                        *self.pregame_match() = Some(refreshed.clone());
+                        pregame = Some(refreshed);
+                    }
+                    Err(err) => log::warn!(
+                        "Failed to refresh pregame match, using previous selections: {}",
+                        err
+                    ),
+                }
+            }
         }
-        while i < agents.len()
-            && self.lock_agent(agents[i].uuid.as_str()).await.is_err()
-        {
-            log::error!("Failed to lock agent {}", &agents[i].name);
-            i += 1;
+        let agents = match &pregame {
+            Some(pregame) => self.skip_taken_agents(agents, pregame, config),
+            None => agents,
+        };
+        let strategy = config.lock_strategy;
+        // the hover delay is only waited once, fallbacks are locked directly
+        let mut hover_delay = match strategy {
+            LockStrategy::HoverThenLock { delay_ms } => {
+                Some(Duration::from_millis(delay_ms))
+            }
+            _ => None,
+        };
+        let mut i = 0;
+        while i < agents.len() {
+            let agent = &agents[i];
+            let res = match (strategy, hover_delay) {
+                (LockStrategy::HoverOnly, _) => {
+                    self.pick_agent(
+                        &agent.uuid,
+                        CharacterSelectionState::Selected,
+                    )
+                    .await
+                }
+                (LockStrategy::HoverThenLock { .. }, Some(delay)) => match self
+                    .pick_agent(&agent.uuid, CharacterSelectionState::Selected)
+                    .await
+                {
+                    Ok(()) => {
+                        hover_delay = None;
+                        let now = chrono::Local::now();
+                        status_line!(
+                            "{} - Hovered {}, locking in {}ms",
+                            now.format("%H:%M:%S"),
+                            console::style(format!("{}", agent.name)).cyan(),
+                            delay.as_millis(),
+                        );
+                        log::info!("Hovered agent: {}", agent.name);
+                        tokio::time::sleep(delay).await;
+                        self.pick_agent(
+                            &agent.uuid,
+                            CharacterSelectionState::Locked,
+                        )
+                        .await
+                    }
+                    Err(err) => Err(err),
+                },
+                _ => {
+                    self.pick_agent(
+                        &agent.uuid,
+                        CharacterSelectionState::Locked,
+                    )
+                    .await
+                }
+            };
+            match res {
+                Ok(()) => break,
+                Err(err) => {
+                    log::error!("Failed to lock agent {}: {err}", agent.name);
+                    if err.is_fatal() {
+                        let now = chrono::Local::now();
+                        status_line!(
+                            "{} - Stopped instalock: {err}",
+                            now.format("%H:%M:%S")
+                        );
+                        return None;
+                    }
+                    i += 1;
+                }
+            }
         }
         if i < agents.len() {
             let failed_attempts = if i > 0 {
@@ -264,18 +594,205 @@ impl ValorantClient {
             } else {
                 "".to_string()
             };
+            let action = match strategy {
+                LockStrategy::HoverOnly => "Hovered",
+                _ => "Instalocked",
+            };
+            let warm = match self.warm_connection_saved() {
+                Some(saved) => {
+                    format!(
+                        ", ~{}ms saved by a warm connection",
+                        saved.as_millis()
+                    )
+                }
+                None => "".to_string(),
+            };
             let now = chrono::Local::now();
-            eprintln!(
-                "{} - Instalocked {} after {}ms{failed_attempts}",
+            status_line!(
+                "{} - {action} {} after {}ms{failed_attempts}{warm}",
                 now.format("%H:%M:%S"),
                 console::style(format!("{}", agents[i].name)).cyan(),
                 tokio::time::Instant::now()
                     .duration_since(begin_event)
                     .as_millis(),
             );
-            log::info!("Locked agent: {}", &agents[i].name);
+            log::info!("{action} agent: {}{warm}", &agents[i].name);
+        } else if !agents.is_empty() {
+            events::emit(LifecycleEvent::AllAgentsFailed {
+                match_id: self.current_match_id().clone(),
+                attempts: agents.len(),
+            });
+        }
+        Some(i < agents.len() && strategy != LockStrategy::HoverOnly)
+    }
+
+    /// Waits until all teammates locked their agents, at most until
+    /// `deadline`
+    async fn wait_for_teammates(&self, deadline: Instant) {
+        loop {
+            match self.get_pregame_match().await {
+                Ok(pregame) => {
+                    let all_locked =
+                        pregame.teammates(&self.subject).all(|player| {
+                            player.character_selection_state
+                                == CharacterSelectionState::Locked
+                        });
+                    *self.pregame_match() = Some(pregame);
+                    if all_locked {
+                        log::info!("All teammates locked their agents");
+                        return;
+                    }
+                }
+                Err(err) if err.is_fatal() => return,
+                Err(err) => log::warn!("Failed to poll pregame match: {err}"),
+            }
+            let next = Instant::now() + TEAMMATES_POLL_INTERVAL;
+            if next >= deadline {
+                sleep_until(deadline).await;
+                return;
+            }
+            sleep_until(next).await;
+        }
+    }
+
+    /// Locks the hovered agent or the first configured one that is still
+    /// available, unless an agent was locked already
+    async fn safety_lock(&self, agents: &[GameAgent], config: &Config) {
+        let pregame = match self.get_pregame_match().await {
+            Ok(pregame) => pregame,
+            Err(err) => {
+                log::warn!("Skipping safety lock: {err}");
+                return;
+            }
+        };
+        *self.pregame_match() = Some(pregame.clone());
+        if pregame.is_locked(&self.subject) {
+            return;
+        }
+        let player = pregame.player(&self.subject);
+        let mut candidates: Vec<AgentId> = player
+            .and_then(|p| p.character())
+            .map(str::to_string)
+            .into_iter()
+            .collect();
+        for agent in agents {
+            if !candidates.contains(&agent.uuid)
+                && pregame.selected_by_teammate(&self.subject, &agent.uuid)
+                    != Some(CharacterSelectionState::Locked)
+            {
+                candidates.push(agent.uuid.clone());
+            }
+        }
+        if candidates.is_empty() {
+            log::warn!("Skipping safety lock, all agents are taken");
+            return;
+        }
+        let now = chrono::Local::now();
+        status_line!(
+            "{} - {}",
+            now.format("%H:%M:%S"),
+            console::style("Agent select ends soon, locking").yellow()
+        );
+        log::warn!(
+            "Safety lock with {}ms left",
+            config.safety_lock_ms.unwrap_or_default()
+        );
+        for agent in candidates {
+            match self
+                .pick_agent(&agent, CharacterSelectionState::Locked)
+                .await
+            {
+                Ok(()) => {
+                    let now = chrono::Local::now();
+                    status_line!(
+                        "{} - Locked {}",
+                        now.format("%H:%M:%S"),
+                        console::style(config::agent_name(&agent)).cyan()
+                    );
+                    return;
+                }
+                Err(err) if err.is_fatal() => break,
+                Err(err) => log::error!("Safety lock failed: {err}"),
+            }
         }
-        Some(())
+    }
+
+    /// Locks the first configured agent that teammates did not take, after
+    /// the one picked last if `after_current` is set
+    async fn lock_fallback(&self, after_current: bool) -> anyhow::Result<()> {
+        let pregame = self.get_pregame_match().await?;
+        *self.pregame_match() = Some(pregame.clone());
+        let config = self.config.borrow().clone();
+        let agents = config.get_agents(&pregame.map_url, Some(pregame.queue()));
+        let current = self.selected_agent.lock().clone();
+        let start = current
+            .filter(|_| after_current)
+            .and_then(|(current, _)| {
+                agents.iter().position(|agent| agent.uuid == current)
+            })
+            .map_or(0, |i| i + 1);
+        let agents =
+            self.skip_taken_agents(agents[start..].to_vec(), &pregame, &config);
+        for agent in agents {
+            match self
+                .pick_agent(&agent.uuid, CharacterSelectionState::Locked)
+                .await
+            {
+                Ok(()) => {
+                    let now = chrono::Local::now();
+                    status_line!(
+                        "{} - Locked {}",
+                        now.format("%H:%M:%S"),
+                        console::style(format!("{}", agent.name)).cyan(),
+                    );
+                    log::info!("Locked fallback agent: {}", agent.name);
+                    return Ok(());
+                }
+                Err(err) => {
+                    log::error!("Failed to lock agent {}: {err}", agent.name);
+                    if err.is_fatal() {
+                        return Err(err.into());
+                    }
+                }
+            }
+        }
+        anyhow::bail!("No configured agent left to lock")
+    }
+
+    /// Drops agents teammates have already locked, and hovered ones as well
+    /// if configured, so we don't waste requests on them
+    fn skip_taken_agents(
+        &self,
+        agents: Vec<GameAgent>,
+        pregame: &PregameMatch,
+        config: &Config,
+    ) -> Vec<GameAgent> {
+        agents
+            .into_iter()
+            .filter(|agent| {
+                let reason = match pregame
+                    .selected_by_teammate(&self.subject, &agent.uuid)
+                {
+                    Some(CharacterSelectionState::Locked) => {
+                        "already locked by a teammate"
+                    }
+                    Some(CharacterSelectionState::Selected)
+                        if config.skip_hovered_agents =>
+                    {
+                        "hovered by a teammate"
+                    }
+                    _ => return true,
+                };
+                let now = chrono::Local::now();
+                status_line!(
+                    "{} - Skipping {} ({reason})",
+                    now.format("%H:%M:%S"),
+                    console::style(format!("{}", agent.name)).cyan(),
+                );
+                log::info!("Skipping agent {}: {reason}", agent.name);
+                false
+            })
+            .collect()
     }
 }
 
@@ -308,7 +825,12 @@ impl ValorantClientHandle {
                     ValorantCommand::QuitPregame => {
                         log::info!("Quitting pregame");
                         match client.quit_pregame().await {
-                            Ok(_) => log::info!("Pregame quit successfully"),
+                            Ok(_) => {
+                                log::info!("Pregame quit successfully");
+                                events::emit(LifecycleEvent::Dodged {
+                                    match_id: client.current_match_id().clone(),
+                                });
+                            }
                             Err(err) => {
                                 log::error!("Failed to quit pregame: {}", err)
                             }
@@ -323,6 +845,45 @@ impl ValorantClientHandle {
                             }
                         }
                     }
+                    ValorantCommand::LockAgent(agent_id) => {
+                        log::info!("Locking agent {agent_id}");
+                        if let Err(err) = client
+                            .pick_agent(
+                                &agent_id,
+                                CharacterSelectionState::Locked,
+                            )
+                            .await
+                        {
+                            log::error!("Failed to lock agent: {}", err)
+                        }
+                    }
+                    ValorantCommand::SelectAgent(agent_id) => {
+                        log::info!("Hovering agent {agent_id}");
+                        if let Err(err) = client
+                            .pick_agent(
+                                &agent_id,
+                                CharacterSelectionState::Selected,
+                            )
+                            .await
+                        {
+                            log::error!("Failed to hover agent: {}", err)
+                        }
+                    }
+                    ValorantCommand::LockNextAgent => {
+                        log::info!("Locking next agent");
+                        if let Err(err) = client.lock_fallback(true).await {
+                            log::error!("Failed to lock next agent: {}", err)
+                        }
+                    }
+                    ValorantCommand::RetryFallback => {
+                        log::info!("Retrying configured agents");
+                        if let Err(err) = client.lock_fallback(false).await {
+                            log::error!(
+                                "Failed to lock configured agent: {}",
+                                err
+                            )
+                        }
+                    }
                 }
             }
         });
@@ -421,9 +982,9 @@ impl ValorantClientHandle {
                         if client.loop_state() == GameLoopState::Pregame {
                             continue;
                         }
-                        client.set_loop_state(GameLoopState::Pregame);
                         *client.current_match_id() = Some(match_id);
-                        let _ = client.handle_pregame(true).await;
+                        client.set_loop_state(GameLoopState::Pregame);
+                        client.spawn_handle_pregame(true);
                     }
                     ValorantEvent::ClientInfo(ClientStatus {
                         subject,
@@ -434,10 +995,14 @@ impl ValorantClientHandle {
                             continue;
                         }
                         let now = chrono::Local::now();
-                        eprintln!("{} - Match started", now.format("%H:%M:%S"));
+                        status_line!(
+                            "{} - Match started",
+                            now.format("%H:%M:%S")
+                        );
                         log::info!("Match started: {match_id}");
-                        client.set_loop_state(GameLoopState::Ingame);
                         *client.current_match_id() = Some(match_id);
+                        *client.pregame_match() = None;
+                        client.set_loop_state(GameLoopState::Ingame);
                     }
                     ValorantEvent::ClientInfo(ClientStatus {
                         subject,
@@ -448,10 +1013,14 @@ impl ValorantClientHandle {
                             continue;
                         }
                         let now = chrono::Local::now();
-                        eprintln!("{} - Match ended", now.format("%H:%M:%S"));
+                        status_line!(
+                            "{} - Match ended",
+                            now.format("%H:%M:%S")
+                        );
                         log::info!("Pregame/Match ended");
-                        client.set_loop_state(GameLoopState::Menus);
                         *client.current_match_id() = None;
+                        *client.pregame_match() = None;
+                        client.set_loop_state(GameLoopState::Menus);
                     }
                 }
             }
@@ -460,16 +1029,27 @@ impl ValorantClientHandle {
 
     pub async fn start(
         lockfile: Lockfile,
-        config: Config,
+        config: watch::Receiver<Config>,
     ) -> anyhow::Result<Self> {
-        let stream = ValorantEventStream::connect(&lockfile).await?;
+        let endpoints = Endpoints::resolve(&config.borrow().endpoints);
+        let stream =
+            ValorantEventStream::connect(&lockfile, &endpoints).await?;
         let (cmd_tx, cmd_rx) = channel(100);
         let client_state = Arc::new(Mutex::new(
             MaybeValorantClient::init(lockfile, config).await,
         ));
         Self::spawn_cmd_handler(cmd_rx, Arc::clone(&client_state));
-        Self::spawn_event_handler(client_state, stream);
-        Ok(Self { tx: cmd_tx })
+        Self::spawn_event_handler(Arc::clone(&client_state), stream);
+        Ok(Self {
+            tx: cmd_tx,
+            client_state,
+        })
+    }
+
+    /// `None` while the client is not initialized
+    pub fn status(&self) -> Option<ClientSnapshot> {
+        // the lock is held while retrying to initialize the client
+        Some(self.client_state.try_lock()?.client()?.status())
     }
 
     // This does only wait for the command to be sent to the client
@@ -481,4 +1061,480 @@ impl ValorantClientHandle {
     pub async fn quit_game(&self) {
         self.tx.send(ValorantCommand::QuitGame).await.unwrap();
     }
+
+    pub async fn lock_agent(&self, agent_id: AgentId) {
+        self.tx
+            .send(ValorantCommand::LockAgent(agent_id))
+            .await
+            .unwrap();
+    }
+
+    pub async fn select_agent(&self, agent_id: AgentId) {
+        self.tx
+            .send(ValorantCommand::SelectAgent(agent_id))
+            .await
+            .unwrap();
+    }
+
+    pub async fn lock_next_agent(&self) {
+        self.tx.send(ValorantCommand::LockNextAgent).await.unwrap();
+    }
+
+    pub async fn retry_fallback(&self) {
+        self.tx.send(ValorantCommand::RetryFallback).await.unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use crate::config::{AgentConfig, MapAgentConfig};
+    use crate::mock_server::{self, config, start, start_with, MockServer};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_instalock_on_pregame() {
+        let server = MockServer::start().await;
+        let _handle =
+            start(&server, &[mock_server::JETT, mock_server::RAZE]).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.lock_requests(), vec![mock_server::JETT]);
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::JETT));
+    }
+
+    #[tokio::test]
+    async fn test_instalock_falls_back_to_next_agent() {
+        let server = MockServer::start().await;
+        server.reject_lock(mock_server::JETT);
+        let _handle =
+            start(&server, &[mock_server::JETT, mock_server::RAZE]).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 2).await;
+        assert_eq!(
+            server.lock_requests(),
+            vec![mock_server::JETT, mock_server::RAZE]
+        );
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::RAZE));
+    }
+
+    #[tokio::test]
+    async fn test_skip_agent_locked_by_teammate() {
+        let server = MockServer::start().await;
+        server.add_teammate("teammate-1", mock_server::JETT, true);
+        server.add_teammate("teammate-2", mock_server::RAZE, false);
+        let _handle = start(
+            &server,
+            &[mock_server::JETT, mock_server::RAZE, mock_server::REYNA],
+        )
+        .await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        // hovered agents are only skipped if configured
+        assert_eq!(server.lock_requests(), vec![mock_server::RAZE]);
+    }
+
+    #[tokio::test]
+    async fn test_skip_agent_hovered_by_teammate() {
+        let server = MockServer::start().await;
+        server.add_teammate("teammate-1", mock_server::JETT, true);
+        server.add_teammate("teammate-2", mock_server::RAZE, false);
+        let (_handle, _config_tx) = start_with(
+            &server,
+            Config {
+                skip_hovered_agents: true,
+                ..config(
+                    &server,
+                    &[mock_server::JETT, mock_server::RAZE, mock_server::REYNA],
+                )
+            },
+        )
+        .await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.lock_requests(), vec![mock_server::REYNA]);
+    }
+
+    #[tokio::test]
+    async fn test_hover_only() {
+        let server = MockServer::start().await;
+        let (_handle, _config_tx) = start_with(
+            &server,
+            Config {
+                lock_strategy: LockStrategy::HoverOnly,
+                ..config(&server, &[mock_server::JETT, mock_server::RAZE])
+            },
+        )
+        .await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/select/", 1).await;
+        assert_eq!(server.select_requests(), vec![mock_server::JETT]);
+        assert_eq!(server.hovered_agent().as_deref(), Some(mock_server::JETT));
+        assert!(server.lock_requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hover_then_lock() {
+        let server = MockServer::start().await;
+        let (_handle, _config_tx) = start_with(
+            &server,
+            Config {
+                lock_strategy: LockStrategy::HoverThenLock { delay_ms: 100 },
+                ..config(&server, &[mock_server::JETT, mock_server::RAZE])
+            },
+        )
+        .await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/select/", 1).await;
+        assert!(server.lock_requests().is_empty());
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.select_requests(), vec![mock_server::JETT]);
+        assert_eq!(server.lock_requests(), vec![mock_server::JETT]);
+    }
+
+    #[tokio::test]
+    async fn test_queue_specific_agents() {
+        let server = MockServer::start().await;
+        server.set_queue("unrated");
+        let mut config = config(&server, &[mock_server::JETT]);
+        config.queue_agent_config.insert(
+            Queue::Unrated.id().to_string(),
+            MapAgentConfig::Default(AgentConfig::Some(vec![
+                mock_server::SOVA.into()
+            ])),
+        );
+        config
+            .queue_agent_config
+            .insert(Queue::Competitive.id().to_string(), MapAgentConfig::None);
+        let (_handle, _config_tx) = start_with(&server, config).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.lock_requests(), vec![mock_server::SOVA]);
+    }
+
+    #[tokio::test]
+    async fn test_config_change_applies_to_next_pregame() {
+        let server = MockServer::start().await;
+        let (_handle, config_tx) =
+            start_with(&server, config(&server, &[mock_server::JETT])).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        server.enter_menus();
+        config_tx.send_replace(config(&server, &[mock_server::RAZE]));
+        server.enter_pregame("match-2");
+        server.wait_for_requests("/lock/", 2).await;
+        assert_eq!(
+            server.lock_requests(),
+            vec![mock_server::JETT, mock_server::RAZE]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_map_uses_default_agents() {
+        let server = MockServer::start().await;
+        server.set_map("/Game/Maps/Unreleased/Unreleased");
+        let mut cfg = config(&server, &[]);
+        cfg.map_agent_config = MapAgentConfig::PerSelectedMapOrDefault {
+            default: AgentConfig::Some(vec![mock_server::JETT.into()]),
+            map_agents: HashMap::from([(
+                mock_server::ASCENT.to_string(),
+                AgentConfig::Some(vec![mock_server::RAZE.into()]),
+            )]),
+        };
+        let _handle = start_with(&server, cfg).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.lock_requests(), vec![mock_server::JETT]);
+    }
+
+    #[tokio::test]
+    async fn test_game_loop_transitions() {
+        let server = MockServer::start().await;
+        let _handle = start(&server, &[mock_server::SOVA]).await;
+        server.set_map(mock_server::BIND);
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        server.enter_ingame("match-1");
+        server.enter_menus();
+        server.enter_pregame("match-2");
+        server.wait_for_requests("/lock/", 2).await;
+        assert_eq!(
+            server.lock_requests(),
+            vec![mock_server::SOVA, mock_server::SOVA]
+        );
+        let lock_path =
+            format!("/pregame/v1/matches/match-2/lock/{}", mock_server::SOVA);
+        assert!(server.requests().iter().any(|req| req.path == lock_path));
+    }
+
+    #[tokio::test]
+    async fn test_instalock_when_started_in_pregame() {
+        let server = MockServer::start().await;
+        server.enter_pregame("match-1");
+        let _handle = start(&server, &[mock_server::REYNA]).await;
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.lock_requests(), vec![mock_server::REYNA]);
+    }
+
+    #[tokio::test]
+    async fn test_quit_pregame() {
+        let server = MockServer::start().await;
+        let handle = start(&server, &[]).await;
+        server.enter_pregame("match-1");
+        server
+            .wait_for_requests("/pregame/v1/matches/match-1", 1)
+            .await;
+        handle.quit_pregame().await;
+        server.wait_for_requests("/quit", 1).await;
+        assert!(server.lock_requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited_lock_is_retried() {
+        let server = MockServer::start().await;
+        server.rate_limit_locks(1);
+        let _handle =
+            start(&server, &[mock_server::JETT, mock_server::RAZE]).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 2).await;
+        // the policy retries the same agent rather than moving on
+        assert_eq!(
+            server.lock_requests(),
+            vec![mock_server::JETT, mock_server::JETT]
+        );
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::JETT));
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy() {
+        let server = MockServer::start().await;
+        server.fail_locks(2);
+        let _handle = start(&server, &[mock_server::JETT]).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 3).await;
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::JETT));
+
+        // only one attempt, the failure moves on to the next agent
+        let server = MockServer::start().await;
+        server.fail_locks(1);
+        let mut config =
+            config(&server, &[mock_server::JETT, mock_server::RAZE]);
+        config.retry.lock.max_attempts = 1;
+        let _handle = start_with(&server, config).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 2).await;
+        assert_eq!(
+            server.lock_requests(),
+            vec![mock_server::JETT, mock_server::RAZE]
+        );
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::RAZE));
+    }
+
+    #[tokio::test]
+    async fn test_replayed_lock_that_landed() {
+        let server = MockServer::start().await;
+        server.lock_slowly(Duration::from_millis(500));
+        let mut cfg = config(&server, &[mock_server::JETT, mock_server::RAZE]);
+        cfg.retry.lock.attempt_timeout_ms = 100;
+        let _handle = start_with(&server, cfg).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 2).await;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        // the retry is rejected, but the timed out attempt locked Jett
+        assert_eq!(
+            server.lock_requests(),
+            vec![mock_server::JETT, mock_server::JETT]
+        );
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::JETT));
+    }
+
+    #[tokio::test]
+    async fn test_keep_glz_connection_warm() {
+        let server = MockServer::start().await;
+        let mut config = config(&server, &[mock_server::JETT]);
+        config.glz_keep_warm_ms = 20;
+        let _handle = start_with(&server, config).await;
+        let pings = || {
+            server
+                .requests()
+                .into_iter()
+                .filter(|req| req.method == "HEAD")
+                .count()
+        };
+        // one over a new connection, the others over the pooled one
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while pings() < 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::JETT));
+    }
+
+    #[tokio::test]
+    async fn test_lock_before_phase_end() {
+        let server = MockServer::start().await;
+        server.set_phase_time_remaining(Duration::from_millis(1500));
+        let mut config = config(&server, &[mock_server::JETT]);
+        config.lock_timing = LockTiming::BeforeEnd { ms: 1000 };
+        config.safety_lock_ms = Some(200);
+        let (handle, _config_tx) = start_with(&server, config).await;
+        let begin = Instant::now();
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        assert!(begin.elapsed() >= Duration::from_millis(500));
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::JETT));
+
+        // locked manually while waiting, the timer doesn't lock again
+        server.enter_menus();
+        server.enter_pregame("match-2");
+        server.wait_for_requests("/matches/match-2", 1).await;
+        handle.lock_agent(mock_server::RAZE.to_string()).await;
+        server.wait_for_requests("/lock/", 2).await;
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        assert_eq!(
+            server.lock_requests(),
+            vec![mock_server::JETT, mock_server::RAZE]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_lock_after_teammates() {
+        let server = MockServer::start().await;
+        server.add_teammate("teammate-1", mock_server::RAZE, true);
+        let mut cfg = config(&server, &[mock_server::RAZE, mock_server::JETT]);
+        cfg.lock_timing = LockTiming::AsLateAsPossible { min_left_ms: 1000 };
+        let _handle = start_with(&server, cfg).await;
+        // all teammates are locked, no need to wait for the timer
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.lock_requests(), vec![mock_server::JETT]);
+
+        // a teammate is still hovering, wait until the timer runs low
+        let server = MockServer::start().await;
+        server.set_phase_time_remaining(Duration::from_millis(1500));
+        server.add_teammate("teammate-1", mock_server::RAZE, false);
+        let mut cfg = config(&server, &[mock_server::JETT]);
+        cfg.lock_timing = LockTiming::AsLateAsPossible { min_left_ms: 1000 };
+        cfg.safety_lock_ms = Some(200);
+        let _handle = start_with(&server, cfg).await;
+        let begin = Instant::now();
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        assert!(begin.elapsed() >= Duration::from_millis(500));
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::JETT));
+    }
+
+    #[tokio::test]
+    async fn test_safety_lock() {
+        let server = MockServer::start().await;
+        server.set_phase_time_remaining(Duration::from_millis(800));
+        let mut config = config(&server, &[mock_server::JETT]);
+        config.lock_strategy = LockStrategy::HoverOnly;
+        config.safety_lock_ms = Some(500);
+        let _handle = start_with(&server, config).await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/select/", 1).await;
+        assert!(server.lock_requests().is_empty());
+        // the hovered agent is locked before the timer runs out
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::JETT));
+    }
+
+    #[tokio::test]
+    async fn test_no_safety_lock_without_agents() {
+        let server = MockServer::start().await;
+        server.set_phase_time_remaining(Duration::from_millis(800));
+        let mut config = config(&server, &[]);
+        config.safety_lock_ms = Some(500);
+        let _handle = start_with(&server, config).await;
+        server.enter_pregame("match-1");
+        tokio::time::sleep(Duration::from_millis(1000)).await;
+        assert!(server.lock_requests().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_tokens_when_rejected() {
+        let server = MockServer::start().await;
+        let _handle = start(&server, &[mock_server::JETT]).await;
+        server.rotate_tokens(2);
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::JETT));
+        let paths: Vec<_> = server
+            .requests()
+            .into_iter()
+            .map(|req| req.path)
+            .filter(|path| {
+                path == "/entitlements/v1/token"
+                    || path == "/pregame/v1/matches/match-1"
+            })
+            .collect();
+        // initial tokens, the rejected request, new tokens and the replay
+        assert_eq!(
+            paths,
+            vec![
+                "/entitlements/v1/token",
+                "/pregame/v1/matches/match-1",
+                "/entitlements/v1/token",
+                "/pregame/v1/matches/match-1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_token_refresh_is_replayed_once() {
+        let server = MockServer::start().await;
+        let handle = start(&server, &[]).await;
+        server.enter_pregame("match-1");
+        server
+            .wait_for_requests("/pregame/v1/matches/match-1", 1)
+            .await;
+        server.revoke_tokens();
+        handle.lock_agent(mock_server::SOVA.to_string()).await;
+        server.wait_for_requests("/lock/", 2).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            server.lock_requests(),
+            vec![mock_server::SOVA, mock_server::SOVA]
+        );
+        let refreshes = server
+            .requests()
+            .iter()
+            .filter(|req| req.path == "/entitlements/v1/token")
+            .count();
+        assert_eq!(refreshes, 2);
+    }
+
+    #[tokio::test]
+    async fn test_retry_from_hover() {
+        let server = MockServer::start().await;
+        server.add_teammate("teammate-1", mock_server::JETT, true);
+        let (handle, _config_tx) = start_with(
+            &server,
+            Config {
+                lock_strategy: LockStrategy::HoverOnly,
+                ..config(&server, &[mock_server::JETT, mock_server::RAZE])
+            },
+        )
+        .await;
+        server.enter_pregame("match-1");
+        server.wait_for_requests("/select/", 1).await;
+        assert_eq!(server.select_requests(), vec![mock_server::RAZE]);
+        assert!(server.lock_requests().is_empty());
+        // the configured agents again, without the one the teammate took
+        handle.retry_fallback().await;
+        server.wait_for_requests("/lock/", 1).await;
+        assert_eq!(server.lock_requests(), vec![mock_server::RAZE]);
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::RAZE));
+        // a locked agent is final
+        handle.lock_agent(mock_server::SOVA.to_string()).await;
+        server.wait_for_requests("/lock/", 2).await;
+        assert_eq!(server.locked_agent().as_deref(), Some(mock_server::RAZE));
+    }
 }
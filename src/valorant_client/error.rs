@@ -0,0 +1,237 @@
+use std::{fmt::Display, time::Duration};
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use serde::Deserialize;
+
+/// The most we wait for `Retry-After`, agent select does not wait for us
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(3);
+
+pub type ApiResult<T> = Result<T, ValorantApiError>;
+
+/// Error body of the Riot apis, e.g.
+/// `{"httpStatus":404,"errorCode":"MATCH_NOT_FOUND","message":"..."}`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RiotError {
+    #[serde(default)]
+    pub error_code: String,
+    #[serde(default)]
+    pub message: String,
+}
+
+impl Display for RiotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.error_code.is_empty(), self.message.is_empty()) {
+            (true, true) => Ok(()),
+            (false, true) => write!(f, ": {}", self.error_code),
+            (true, false) => write!(f, ": {}", self.message),
+            (false, false) => {
+                write!(f, ": {} ({})", self.error_code, self.message)
+            }
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ValorantApiError {
+    /// 404, e.g. when the pregame is over
+    #[error("Not found{0}")]
+    NotFound(RiotError),
+    /// 400 or 409, e.g. the agent is taken or not owned
+    #[error("Rejected with {status}{body}")]
+    Rejected { status: StatusCode, body: RiotError },
+    /// 401 or 403, the tokens expired
+    #[error("Unauthorized with {status}{body}")]
+    Unauthorized { status: StatusCode, body: RiotError },
+    /// 429
+    #[error("Rate limited{body}")]
+    RateLimited {
+        retry_after: Option<Duration>,
+        body: RiotError,
+    },
+    #[error("Request timed out")]
+    Timeout,
+    /// Any other error status, e.g. 5xx
+    #[error("Request failed with {status}{body}")]
+    Status { status: StatusCode, body: RiotError },
+    #[error("Request failed: {0}")]
+    Connection(#[source] reqwest::Error),
+    #[error("Invalid response: {0}")]
+    Decode(#[from] serde_json::Error),
+    #[error("No MatchID available")]
+    NoMatchId,
+    /// A retry was rejected after an earlier attempt failed without a
+    /// response, so the earlier one may have been applied anyway
+    #[error("{0}, after an attempt that may have been applied")]
+    Replayed(Box<ValorantApiError>),
+}
+
+impl From<reqwest::Error> for ValorantApiError {
+    fn from(err: reqwest::Error) -> Self {
+        match err.is_timeout() {
+            true => Self::Timeout,
+            false => Self::Connection(err),
+        }
+    }
+}
+
+impl ValorantApiError {
+    /// Passes successful responses through, turns the others into errors
+    pub async fn check(res: Response) -> ApiResult<Response> {
+        let status = res.status();
+        if status.is_success() {
+            return Ok(res);
+        }
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok()?.parse().ok())
+            .map(Duration::from_secs);
+        let text = res.text().await.unwrap_or_default();
+        log::debug!("error response ({status}): {text}");
+        // some errors come without a body, or with html from a proxy
+        let body = serde_json::from_str(&text).unwrap_or_default();
+        Err(match status {
+            StatusCode::NOT_FOUND => Self::NotFound(body),
+            StatusCode::BAD_REQUEST | StatusCode::CONFLICT => {
+                Self::Rejected { status, body }
+            }
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+                Self::Unauthorized { status, body }
+            }
+            StatusCode::TOO_MANY_REQUESTS => {
+                Self::RateLimited { retry_after, body }
+            }
+            _ => Self::Status { status, body },
+        })
+    }
+
+    pub fn riot_error(&self) -> Option<&RiotError> {
+        match self {
+            Self::NotFound(body)
+            | Self::Rejected { body, .. }
+            | Self::Unauthorized { body, .. }
+            | Self::RateLimited { body, .. }
+            | Self::Status { body, .. } => Some(body),
+            Self::Replayed(err) => err.riot_error(),
+            _ => None,
+        }
+    }
+
+    /// The error status, `None` if there was no response
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::NotFound(_) => Some(StatusCode::NOT_FOUND),
+            Self::RateLimited { .. } => Some(StatusCode::TOO_MANY_REQUESTS),
+            Self::Rejected { status, .. }
+            | Self::Unauthorized { status, .. }
+            | Self::Status { status, .. } => Some(*status),
+            Self::Replayed(err) => err.status(),
+            _ => None,
+        }
+    }
+
+    /// `Retry-After` of a rate limited request, `None` for other errors or
+    /// without the header
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after, .. } => {
+                retry_after.map(|d| d.min(MAX_RETRY_AFTER))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the request may have reached the server although there was
+    /// no response, so sending it again could apply it twice
+    pub fn maybe_applied(&self) -> bool {
+        match self {
+            Self::Timeout => true,
+            Self::Connection(err) => !err.is_connect() && !err.is_builder(),
+            _ => false,
+        }
+    }
+
+    /// Whether other requests of the same kind are bound to fail as well,
+    /// e.g. locking another agent after the pregame is gone
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Self::NotFound(_) | Self::Unauthorized { .. } | Self::NoMatchId
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &str) -> Response {
+        let mut builder = http::Response::builder().status(status);
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(body.to_string()).unwrap().into()
+    }
+
+    #[tokio::test]
+    async fn test_check_status() {
+        let body = r#"{"httpStatus":409,"errorCode":"CHARACTER_ALREADY_LOCKED","message":"taken"}"#;
+        let err = ValorantApiError::check(response(409, &[], body))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ValorantApiError::Rejected { .. }));
+        assert!(!err.is_fatal());
+        assert_eq!(
+            err.riot_error().unwrap().error_code,
+            "CHARACTER_ALREADY_LOCKED"
+        );
+        assert_eq!(
+            err.to_string(),
+            "Rejected with 409 Conflict: CHARACTER_ALREADY_LOCKED (taken)"
+        );
+
+        let err = ValorantApiError::check(response(404, &[], "not json"))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ValorantApiError::NotFound(_)));
+        assert!(err.is_fatal());
+        assert_eq!(err.to_string(), "Not found");
+
+        let err = ValorantApiError::check(response(401, &[], ""))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ValorantApiError::Unauthorized { .. }));
+
+        let err = ValorantApiError::check(response(503, &[], ""))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ValorantApiError::Status { .. }));
+        assert!(!err.is_fatal());
+
+        assert!(ValorantApiError::check(response(204, &[], ""))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limited() {
+        let err =
+            ValorantApiError::check(response(429, &[("Retry-After", "1")], ""))
+                .await
+                .unwrap_err();
+        assert_eq!(err.retry_after(), Some(Duration::from_secs(1)));
+        let err = ValorantApiError::check(response(
+            429,
+            &[("Retry-After", "120")],
+            "",
+        ))
+        .await
+        .unwrap_err();
+        assert_eq!(err.retry_after(), Some(MAX_RETRY_AFTER));
+        let err = ValorantApiError::check(response(429, &[], ""))
+            .await
+            .unwrap_err();
+        assert_eq!(err.retry_after(), None);
+    }
+}
@@ -1,100 +1,144 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
-use crate::lockfile::Lockfile;
-use anyhow::{Context, Result};
+use crate::{endpoints::Endpoints, lockfile::Lockfile};
 use reqwest::{Client, RequestBuilder, Response};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
 
-use super::{types::ValorantClientAuth, ValorantClient};
+use super::{
+    error::{ApiResult, ValorantApiError},
+    retry::RetryPolicy,
+    types::ValorantClientAuth,
+    ValorantClient,
+};
 
 const RIOT_ENTITLEMENTS_HEADER: &str = "X-Riot-Entitlements-JWT";
 const RIOT_CLIENT_VERSION_HEADER: &str = "X-Riot-ClientVersion";
 const RIOT_CLIENT_PLATFORM_HEADER: &str = "X-Riot-ClientPlatform";
 
-/// https://127.0.0.1:{port}/product-session/v1/external-sessions
-
 impl ValorantClient {
     pub async fn sessions_info(
         client: &Client,
+        endpoints: &Endpoints,
         lockfile: &Lockfile,
-    ) -> Result<SessionsResponse> {
+        policy: &RetryPolicy,
+    ) -> ApiResult<SessionsResponse> {
         log::debug!("Sending session info request. lockfile: {:#?}", lockfile);
-        let res = send_with_retry(with_local_auth(
-            client.get(format!(
-                "{}product-session/v1/external-sessions",
-                lockfile.http_addr()
-            )),
-            lockfile,
-        ))
-        .await?
-        .error_for_status()?
-        .text()
-        //.json()
-        .await?;
+        let res = policy
+            .send(with_local_auth(
+                client.get(endpoints.local_http(
+                    lockfile,
+                    "product-session/v1/external-sessions",
+                )),
+                lockfile,
+            ))
+            .await?
+            .text()
+            //.json()
+            .await?;
         log::debug!("sessions info response: {:#?}", res);
         serde_json::from_str(&res).map_err(Into::into)
     }
 
     pub async fn fetch_auth_tokens(
         client: &Client,
+        endpoints: &Endpoints,
         lockfile: &Lockfile,
-    ) -> Result<ValorantClientAuth> {
+        policy: &RetryPolicy,
+    ) -> ApiResult<ValorantClientAuth> {
         log::debug!("Sending auth tokens request. lockfile: {:#?}", lockfile);
-        let res = send_with_retry(with_local_auth(
-            client
-                .get(format!("{}entitlements/v1/token", lockfile.http_addr())),
-            lockfile,
-        ))
-        .await?
-        .error_for_status()?
-        .json()
-        .await
-        .map_err(Into::into);
+        let res = policy
+            .send(with_local_auth(
+                client.get(
+                    endpoints.local_http(lockfile, "entitlements/v1/token"),
+                ),
+                lockfile,
+            ))
+            .await?
+            .text()
+            .await?;
         log::debug!("fetch auth tokens response: {:#?}", res);
-        res
+        serde_json::from_str(&res).map_err(Into::into)
     }
 
-    pub async fn quit_pregame(&self) -> Result<()> {
+    pub async fn quit_pregame(&self) -> ApiResult<()> {
         log::debug!(
             "Sending quit pregame match request: {}",
             self.current_match_id()
                 .as_ref()
-                .context("No MatchID available")?
+                .ok_or(ValorantApiError::NoMatchId)?
         );
-        let res =
-            send_with_retry(self.with_remote_auth(self.client.post(format!(
-                "https://glz-{}-1.{}.a.pvp.net/pregame/v1/matches/{}/quit",
-                &self.region,
-                &self.shard,
+        let res = self
+            .send_remote(self.client.post(self.glz(&format!(
+                "pregame/v1/matches/{}/quit",
                 self.current_match_id()
                     .as_ref()
-                    .context("No MatchID available")?
+                    .ok_or(ValorantApiError::NoMatchId)?
             ))))
-            .await?
-            .error_for_status()?;
+            .await?;
         log::debug!("quit pregame response: {res:#?}");
         log::debug!("quit pregame response body: {:#?}", res.text().await);
         Ok(())
     }
 
-    pub async fn lock_agent(&self, agent_id: &str) -> Result<()> {
+    pub async fn lock_agent(&self, agent_id: &str) -> ApiResult<()> {
         log::debug!(
             "Sending lock agent request: {agent_id}, {}",
             self.current_match_id()
                 .as_ref()
-                .context("No MatchID available")?
+                .ok_or(ValorantApiError::NoMatchId)?
         );
-        let res = send_with_retry(self.with_remote_auth(self.client.post(format!("https://glz-{}-1.{}.a.pvp.net/pregame/v1/matches/{}/lock/{agent_id}",
-		&self.region,
-		&self.shard,
+        let policy = self.config.borrow().retry.lock.clone();
+        let res = match self.send_remote_with(self.client.post(self.glz(&format!("pregame/v1/matches/{}/lock/{agent_id}",
 		self.current_match_id()
 			.as_ref()
-			.context("No MatchID available")?)))).await?.error_for_status()?;
+			.ok_or(ValorantApiError::NoMatchId)?))), &policy).await {
+            // the attempt that timed out may have locked the agent already
+            Err(ValorantApiError::Replayed(err)) => {
+                return match self.get_pregame_match().await {
+                    Ok(pregame)
+                        if pregame.is_locked_as(&self.subject, agent_id) =>
+                    {
+                        log::info!(
+                            "Lock retry was rejected, the first attempt locked {agent_id}"
+                        );
+                        Ok(())
+                    }
+                    _ => Err(*err),
+                };
+            }
+            res => res?,
+        };
         log::debug!("lock agent response: {res:#?}");
         log::debug!("lock agent response body: {:#?}", res.text().await);
         Ok(())
     }
 
+    /// Hovers the agent without locking it
+    pub async fn select_agent(&self, agent_id: &str) -> ApiResult<()> {
+        log::debug!(
+            "Sending select agent request: {agent_id}, {}",
+            self.current_match_id()
+                .as_ref()
+                .ok_or(ValorantApiError::NoMatchId)?
+        );
+        let policy = self.config.borrow().retry.lock.clone();
+        let res = self
+            .send_remote_with(
+                self.client.post(self.glz(&format!(
+                    "pregame/v1/matches/{}/select/{agent_id}",
+                    self.current_match_id()
+                        .as_ref()
+                        .ok_or(ValorantApiError::NoMatchId)?
+                ))),
+                &policy,
+            )
+            .await?;
+        log::debug!("select agent response: {res:#?}");
+        log::debug!("select agent response body: {:#?}", res.text().await);
+        Ok(())
+    }
+
     // https://auth.riotgames.com/userinfo
     /*pub async fn current_player(
         &self,
@@ -114,25 +158,22 @@ impl ValorantClient {
         res.map_err(Into::into)
     }*/
 
-    pub async fn get_pregame_match(&self) -> Result<PregameMatch> {
+    pub async fn get_pregame_match(&self) -> ApiResult<PregameMatch> {
         log::debug!(
             "Sending get pregame match request: {}",
             &self
                 .current_match_id()
                 .as_ref()
-                .context("No MatchID available")?
+                .ok_or(ValorantApiError::NoMatchId)?
         );
-        let res =
-            send_with_retry(self.with_remote_auth(self.client.get(format!(
-                "https://glz-{}-1.{}.a.pvp.net/pregame/v1/matches/{}",
-                &self.region,
-                &self.shard,
+        let res = self
+            .send_remote(self.client.get(self.glz(&format!(
+                "pregame/v1/matches/{}",
                 self.current_match_id()
                     .as_ref()
-                    .context("No MatchID available")?
+                    .ok_or(ValorantApiError::NoMatchId)?
             ))))
-            .await?
-            .error_for_status()?;
+            .await?;
         log::debug!("get pregame match response: {res:#?}");
         let res = res.text().await;
         log::debug!("get pregame match response body: {res:#?}");
@@ -140,15 +181,13 @@ impl ValorantClient {
     }
 
     //https://glz-{region}-1.{shard}.a.pvp.net/pregame/v1/players/{puuid}
-    pub async fn current_pregame(&self) -> Result<CurrentPlayerPregame> {
+    pub async fn current_pregame(&self) -> ApiResult<CurrentPlayerPregame> {
         log::debug!("Sending current pregame match request: {}", &self.subject);
-        let res =
-            send_with_retry(self.with_remote_auth(self.client.get(format!(
-                "https://glz-{}-1.{}.a.pvp.net/pregame/v1/players/{}",
-                &self.region, &self.shard, &self.subject
-            ))))
-            .await?
-            .error_for_status()?;
+        let res = self
+            .send_remote(self.client.get(
+                self.glz(&format!("pregame/v1/players/{}", &self.subject)),
+            ))
+            .await?;
         log::debug!("current pregame response: {res:#?}");
         let res = res.text().await;
         log::debug!("current pregame response body: {res:#?}");
@@ -156,35 +195,87 @@ impl ValorantClient {
     }
 
     //https://glz-{region}-1.{shard}.a.pvp.net/core-game/v1/players/{puuid}/disassociate/{current game match id}
-    pub async fn quit_ingame(&self) -> Result<()> {
+    pub async fn quit_ingame(&self) -> ApiResult<()> {
         log::debug!(
             "Sending quit ingame match request: {}",
             self.current_match_id()
                 .as_ref()
-                .context("No MatchID available")?
+                .ok_or(ValorantApiError::NoMatchId)?
         );
-        let res = send_with_retry(self.with_remote_auth(self.client.post(format!("https://glz-{}-1.{}.a.pvp.net/core-game/v1/players/{}/disassociate/{}", &self.region, &self.shard, &self.subject, self.current_match_id().as_ref().context("No MatchID available")?)))).await?.error_for_status()?;
+        let res = self.send_remote(self.client.post(self.glz(&format!("core-game/v1/players/{}/disassociate/{}", &self.subject, self.current_match_id().as_ref().ok_or(ValorantApiError::NoMatchId)?)))).await?;
         log::debug!("quit ingame response: {res:#?}");
         log::debug!("quit ingame response body: {:#?}", res.text().await);
         Ok(())
     }
 
     //https://glz-{region}-1.{shard}.a.pvp.net/core-game/v1/players/{puuid}
-    pub async fn current_ingame(&self) -> Result<CurrentPlayerIngame> {
+    pub async fn current_ingame(&self) -> ApiResult<CurrentPlayerIngame> {
         log::debug!("Sending current ingame match request: {}", &self.subject);
-        let res =
-            send_with_retry(self.with_remote_auth(self.client.get(format!(
-                "https://glz-{}-1.{}.a.pvp.net/core-game/v1/players/{}",
-                &self.region, &self.shard, &self.subject
-            ))))
-            .await?
-            .error_for_status()?;
+        let res = self
+            .send_remote(self.client.get(
+                self.glz(&format!("core-game/v1/players/{}", &self.subject)),
+            ))
+            .await?;
         log::debug!("current ingame response: {res:#?}");
         let res = res.text().await;
         log::debug!("current ingame response body: {res:#?}");
         serde_json::from_str(&res?).map_err(Into::into)
     }
 
+    /// [`Self::send_remote_with`] the default retry policy
+    async fn send_remote(&self, req: RequestBuilder) -> ApiResult<Response> {
+        let policy = self.config.borrow().retry.default.clone();
+        self.send_remote_with(req, &policy).await
+    }
+
+    /// Sends a request with the current tokens and replays it once with
+    /// fresh tokens if they are rejected, e.g. after a missed
+    /// `EntitlementsTokenChanged` event
+    async fn send_remote_with(
+        &self,
+        req: RequestBuilder,
+        policy: &RetryPolicy,
+    ) -> ApiResult<Response> {
+        // the replay only gets what is left of the deadline
+        let deadline = Instant::now() + policy.deadline();
+        let access_token = self.auth().access_token.clone();
+        let first = self.with_remote_auth(req.try_clone().unwrap());
+        let res = policy.send_until(first, deadline).await;
+        let err = match res {
+            Err(err @ ValorantApiError::Unauthorized { .. }) => err,
+            res => return res,
+        };
+        // the websocket event may have delivered new tokens meanwhile
+        if self.auth().access_token == access_token {
+            log::warn!("Tokens were rejected, refreshing them: {err}");
+            self.refresh_auth().await?;
+        }
+        if Instant::now() >= deadline {
+            return Err(err);
+        }
+        policy.send_until(self.with_remote_auth(req), deadline).await
+    }
+
+    /// Fetches new tokens from the local client
+    pub async fn refresh_auth(&self) -> ApiResult<()> {
+        let policy = self.config.borrow().retry.default.clone();
+        let auth = Self::fetch_auth_tokens(
+            &self.client,
+            &self.endpoints,
+            &self.lockfile,
+            &policy,
+        )
+        .await?;
+        *self.auth() = auth;
+        log::info!("Refreshed auth tokens");
+        Ok(())
+    }
+
+    /// Full glz URL for `path` in the current region and shard
+    pub(super) fn glz(&self, path: &str) -> String {
+        self.endpoints.glz(&self.region, &self.shard, path)
+    }
+
     fn with_remote_auth(&self, req: RequestBuilder) -> RequestBuilder {
         let auth = self.auth();
         req.bearer_auth(&auth.access_token)
@@ -206,31 +297,155 @@ pub struct CurrentPlayerPregame {
     pub match_id: String,
 }
 
+/// https://valapidocs.techchrism.me/endpoint/pre-game-match
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
 pub struct PregameMatch {
     #[serde(rename = "ID")]
     pub match_id: String,
     #[serde(rename = "MapID")]
     pub map_url: String,
+    /// `null` e.g. in custom games without teams
+    pub ally_team: Option<PregameTeam>,
+    // character_select_active, provisioned, ...
+    #[serde(default)]
+    pub pregame_state: String,
+    /// Time left in the current phase (agent select) in nanoseconds, 0 if
+    /// unknown
+    #[serde(default, rename = "PhaseTimeRemainingNS")]
+    pub phase_time_remaining_ns: i64,
+    /// competitive, unrated, swiftplay, ... or empty for custom games
+    #[serde(rename = "QueueID")]
+    pub queue_id: String,
+    // /Game/GameModes/Bomb/BombGameMode.BombGameMode_C
+    #[serde(default)]
+    pub mode: String,
+    /// Matchmaking or CustomGame
+    #[serde(default, rename = "ProvisioningFlowID")]
+    pub provisioning_flow_id: String,
+    #[serde(default)]
+    pub is_ranked: bool,
 }
 
-fn with_local_auth(req: RequestBuilder, lockfile: &Lockfile) -> RequestBuilder {
-    req.basic_auth("riot", Some(lockfile.password.clone()))
+impl PregameMatch {
+    /// The `QueueID`, or `custom` for custom games which have none
+    pub fn queue(&self) -> &str {
+        if self.provisioning_flow_id == "CustomGame" || self.queue_id.is_empty()
+        {
+            "custom"
+        } else {
+            &self.queue_id
+        }
+    }
+
+    pub fn phase_time_remaining(&self) -> Duration {
+        Duration::from_nanos(self.phase_time_remaining_ns.max(0) as u64)
+    }
+
+    pub fn ally_players(&self) -> &[PregamePlayer] {
+        self.ally_team
+            .as_ref()
+            .map_or(&[], |team| team.players.as_slice())
+    }
+
+    pub fn player(&self, subject: &str) -> Option<&PregamePlayer> {
+        self.ally_players().iter().find(|p| p.subject == subject)
+    }
+
+    /// Whether the player with the given subject locked an agent
+    pub fn is_locked(&self, subject: &str) -> bool {
+        self.player(subject).is_some_and(|p| {
+            p.character_selection_state == CharacterSelectionState::Locked
+        })
+    }
+
+    /// Whether the player with the given subject locked this agent
+    pub fn is_locked_as(&self, subject: &str, agent_id: &str) -> bool {
+        self.is_locked(subject)
+            && self
+                .player(subject)
+                .is_some_and(|p| p.character_id.eq_ignore_ascii_case(agent_id))
+    }
+
+    /// All ally players except the one with the given subject
+    pub fn teammates<'a>(
+        &'a self,
+        subject: &'a str,
+    ) -> impl Iterator<Item = &'a PregamePlayer> {
+        self.ally_players().iter().filter(move |p| p.subject != subject)
+    }
+
+    /// How a teammate (anyone but `subject`) has selected the agent, locked
+    /// takes precedence over hovered
+    pub fn selected_by_teammate(
+        &self,
+        subject: &str,
+        agent_id: &str,
+    ) -> Option<CharacterSelectionState> {
+        self.teammates(subject)
+            .filter(|p| p.character() == Some(agent_id))
+            .map(|p| p.character_selection_state)
+            .max_by_key(|state| *state == CharacterSelectionState::Locked)
+    }
 }
 
-async fn send_with_retry(req: RequestBuilder) -> Result<Response> {
-    match req.try_clone().unwrap().send().await {
-        Ok(ok) => Ok(ok),
-        Err(err) => {
-            if err.is_timeout() {
-                req.send().await.map_err(Into::into)
-            } else {
-                Err(err.into())
-            }
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PregameTeam {
+    // Red or Blue
+    #[serde(rename = "TeamID")]
+    pub team_id: String,
+    pub players: Vec<PregamePlayer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PregamePlayer {
+    pub subject: String,
+    /// Agent uuid or empty string if nothing was selected yet
+    #[serde(rename = "CharacterID")]
+    pub character_id: String,
+    pub character_selection_state: CharacterSelectionState,
+    pub pregame_player_state: PregamePlayerState,
+    #[serde(default)]
+    pub is_captain: bool,
+}
+
+impl PregamePlayer {
+    /// The agent uuid if the player has hovered or locked an agent
+    pub fn character(&self) -> Option<&str> {
+        match self.character_selection_state {
+            CharacterSelectionState::None => None,
+            _ if self.character_id.is_empty() => None,
+            _ => Some(&self.character_id),
         }
     }
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CharacterSelectionState {
+    #[serde(rename = "")]
+    None,
+    /// hovered
+    Selected,
+    Locked,
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PregamePlayerState {
+    Joined,
+    #[serde(other)]
+    Other,
+}
+
+fn with_local_auth(req: RequestBuilder, lockfile: &Lockfile) -> RequestBuilder {
+    req.basic_auth("riot", Some(lockfile.password.clone()))
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 pub struct PlayerInfo {
     #[serde(rename = "sub")]
@@ -325,6 +540,101 @@ pub struct ClientInfo {
 mod test {
     use super::*;
 
+    const PREGAME_MATCH: &str = r#"{"ID":"affd0370-cd8b-4e7d-8998-ff88fb49b0ab","Version":1711902351253,"Teams":[{"TeamID":"Blue","Players":[]}],"AllyTeam":{"TeamID":"Blue","Players":[{"Subject":"3e62cdbc-c4d0-5408-9de0-74bd1555f4cb","CharacterID":"","CharacterSelectionState":"","PregamePlayerState":"joined","CompetitiveTier":0,"PlayerIdentity":{"Subject":"3e62cdbc-c4d0-5408-9de0-74bd1555f4cb","PlayerCardID":"9fb348bc-41a0-91ad-8a3e-818035c4e561","PlayerTitleID":"","AccountLevel":81,"PreferredLevelBorderID":"","Incognito":false,"HideAccountLevel":false},"SeasonalBadgeInfo":{"SeasonID":"","NumberOfWins":0,"WinsByTier":null,"Rank":0,"LeaderboardRank":0},"IsCaptain":false},{"Subject":"5b0bc2c3-0b52-5b5f-9c2a-0f6b3a3a5f0e","CharacterID":"add6443a-41bd-e414-f6ad-e58d267f4e95","CharacterSelectionState":"locked","PregamePlayerState":"joined","CompetitiveTier":0,"IsCaptain":false},{"Subject":"9d1b3b1c-6f3a-5c4b-8a3e-2b7c0d9e1f2a","CharacterID":"f94c3b30-42be-e959-889c-5aa313dba261","CharacterSelectionState":"selected","PregamePlayerState":"joined","CompetitiveTier":0,"IsCaptain":false}]},"EnemyTeam":null,"ObserverSubjects":[],"MatchCoaches":[],"EnemyTeamSize":5,"EnemyTeamLockCount":0,"PregameState":"character_select_active","LastUpdated":"2024-03-31T16:25:51.253Z","MapID":"/Game/Maps/Ascent/Ascent","MapSelectPool":[],"BannedMapIDs":[],"CastedVotes":{},"MapSelectSteps":[],"MapSelectStep":0,"Team1":"Blue","GamePodID":"aresriot.aws-rclusterprod-euc1-1.eu-gp-frankfurt-1","Mode":"/Game/GameModes/Bomb/BombGameMode.BombGameMode_C","VoiceSessionID":"affd0370-cd8b-4e7d-8998-ff88fb49b0ab-1","MUCName":"","TeamMatchToken":"","QueueID":"unrated","ProvisioningFlowID":"Matchmaking","IsRanked":false,"PhaseTimeRemainingNS":79472964584,"StepTimeRemainingNS":0,"altModesFlagADA":false,"TournamentMetadata":null,"RosterMetadata":null}"#;
+
+    #[test]
+    fn test_parse_pregame_match() {
+        let pregame: PregameMatch =
+            serde_json::from_str(PREGAME_MATCH).unwrap();
+        assert_eq!(pregame.match_id, "affd0370-cd8b-4e7d-8998-ff88fb49b0ab");
+        assert_eq!(pregame.map_url, "/Game/Maps/Ascent/Ascent");
+        assert_eq!(pregame.queue_id, "unrated");
+        assert_eq!(pregame.queue(), "unrated");
+        assert_eq!(pregame.provisioning_flow_id, "Matchmaking");
+        assert!(!pregame.is_ranked);
+        assert_eq!(
+            pregame.phase_time_remaining(),
+            Duration::from_nanos(79472964584)
+        );
+        assert_eq!(pregame.ally_players().len(), 3);
+        let me = pregame
+            .player("3e62cdbc-c4d0-5408-9de0-74bd1555f4cb")
+            .unwrap();
+        assert_eq!(
+            me.character_selection_state,
+            CharacterSelectionState::None
+        );
+        assert_eq!(me.pregame_player_state, PregamePlayerState::Joined);
+        assert_eq!(me.character(), None);
+        let teammates: Vec<_> = pregame
+            .teammates("3e62cdbc-c4d0-5408-9de0-74bd1555f4cb")
+            .map(|p| (p.character(), p.character_selection_state))
+            .collect();
+        assert_eq!(
+            teammates,
+            vec![
+                (
+                    Some("add6443a-41bd-e414-f6ad-e58d267f4e95"),
+                    CharacterSelectionState::Locked
+                ),
+                (
+                    Some("f94c3b30-42be-e959-889c-5aa313dba261"),
+                    CharacterSelectionState::Selected
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_minimal_pregame_match() {
+        let pregame: PregameMatch = serde_json::from_str(
+            r#"{"ID":"match-1","MapID":"/Game/Maps/Ascent/Ascent","AllyTeam":{"TeamID":"Blue","Players":[{"Subject":"me","CharacterID":"","CharacterSelectionState":"banned","PregamePlayerState":"joined"}]},"QueueID":"unrated"}"#,
+        )
+        .unwrap();
+        assert_eq!(pregame.queue(), "unrated");
+        assert_eq!(pregame.phase_time_remaining(), Duration::ZERO);
+        assert_eq!(
+            pregame.player("me").unwrap().character_selection_state,
+            CharacterSelectionState::Unknown
+        );
+    }
+
+    #[test]
+    fn test_selected_by_teammate() {
+        let pregame: PregameMatch =
+            serde_json::from_str(PREGAME_MATCH).unwrap();
+        let me = "3e62cdbc-c4d0-5408-9de0-74bd1555f4cb";
+        assert_eq!(
+            pregame.selected_by_teammate(
+                me,
+                "add6443a-41bd-e414-f6ad-e58d267f4e95"
+            ),
+            Some(CharacterSelectionState::Locked)
+        );
+        assert_eq!(
+            pregame.selected_by_teammate(
+                me,
+                "f94c3b30-42be-e959-889c-5aa313dba261"
+            ),
+            Some(CharacterSelectionState::Selected)
+        );
+        assert_eq!(
+            pregame.selected_by_teammate(
+                me,
+                "a3bfb853-43b2-7238-a4f1-ad90e9e46bcc"
+            ),
+            None
+        );
+        // own selection is never reported
+        assert_eq!(
+            pregame.selected_by_teammate(
+                "5b0bc2c3-0b52-5b5f-9c2a-0f6b3a3a5f0e",
+                "add6443a-41bd-e414-f6ad-e58d267f4e95"
+            ),
+            None
+        );
+    }
+
     #[test]
     fn test_shard() {
         let lc = LaunchConfiguration {
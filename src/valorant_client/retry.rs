@@ -0,0 +1,306 @@
+//! How failed requests are retried, configurable in
+//! [`Config::retry`](crate::config::Config::retry).
+//!
+//! Every attempt has its own timeout, the waits in between grow
+//! exponentially with some jitter, and no attempt is started after the
+//! overall deadline. Locking has to be done within a few seconds, so it gets
+//! its own aggressive policy.
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::{RequestBuilder, Response};
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+
+use super::error::{ApiResult, ValorantApiError};
+
+/// Fields missing in the config are taken from [`RetryPolicy::default`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Including the first one
+    pub max_attempts: u32,
+    pub attempt_timeout_ms: u64,
+    /// Wait before the second attempt, doubled for every further one
+    pub backoff_ms: u64,
+    pub max_backoff_ms: u64,
+    /// Up to this much is added to every wait at random
+    pub jitter_ms: u64,
+    /// Across all attempts and the waits in between
+    pub deadline_ms: u64,
+    /// 429 waits for `Retry-After` if it is longer than the backoff
+    pub retry_statuses: Vec<u16>,
+    /// Timeouts and connection errors, e.g. resets
+    pub retry_connection_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            attempt_timeout_ms: 1500,
+            backoff_ms: 250,
+            max_backoff_ms: 2000,
+            jitter_ms: 100,
+            deadline_ms: 5000,
+            retry_statuses: vec![429, 500, 502, 503, 504],
+            retry_connection_errors: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Retries quickly and gives up early, agent select does not wait
+    pub fn aggressive() -> Self {
+        Self {
+            max_attempts: 5,
+            attempt_timeout_ms: 700,
+            backoff_ms: 20,
+            max_backoff_ms: 150,
+            jitter_ms: 20,
+            deadline_ms: 2500,
+            ..Self::default()
+        }
+    }
+
+    fn attempt_timeout(&self) -> Duration {
+        Duration::from_millis(self.attempt_timeout_ms)
+    }
+
+    pub(super) fn deadline(&self) -> Duration {
+        Duration::from_millis(self.deadline_ms)
+    }
+
+    fn is_retryable(&self, err: &ValorantApiError) -> bool {
+        match err {
+            ValorantApiError::Timeout => self.retry_connection_errors,
+            ValorantApiError::Connection(err) => {
+                self.retry_connection_errors && !err.is_builder()
+            }
+            err => err.status().is_some_and(|status| {
+                self.retry_statuses.contains(&status.as_u16())
+            }),
+        }
+    }
+
+    /// Backoff before attempt `attempts + 1`, without jitter
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 1u64 << attempts.saturating_sub(1).min(16);
+        Duration::from_millis(
+            self.backoff_ms
+                .saturating_mul(factor)
+                .min(self.max_backoff_ms),
+        )
+    }
+
+    /// How long to wait after `attempts` attempts failed with `err`, `None`
+    /// to give up
+    fn next_delay(
+        &self,
+        err: &ValorantApiError,
+        attempts: u32,
+        remaining: Duration,
+    ) -> Option<Duration> {
+        if attempts >= self.max_attempts || !self.is_retryable(err) {
+            return None;
+        }
+        let jitter = rand::thread_rng().gen_range(0..=self.jitter_ms);
+        let mut delay = self.backoff(attempts) + Duration::from_millis(jitter);
+        if let Some(retry_after) = err.retry_after() {
+            delay = delay.max(retry_after);
+        }
+        // no time left for another attempt after waiting
+        (delay < remaining).then_some(delay)
+    }
+
+    /// Sends the request until it succeeds or the policy gives up, error
+    /// statuses are returned as [`ValorantApiError`]. A rejection after an
+    /// attempt that [may have been applied](ValorantApiError::maybe_applied)
+    /// is returned as [`ValorantApiError::Replayed`].
+    pub async fn send(&self, req: RequestBuilder) -> ApiResult<Response> {
+        self.send_until(req, Instant::now() + self.deadline()).await
+    }
+
+    /// Like [`send`](Self::send), but with a deadline that was already
+    /// started, e.g. for the replay of a request
+    pub async fn send_until(
+        &self,
+        req: RequestBuilder,
+        deadline: Instant,
+    ) -> ApiResult<Response> {
+        let mut attempts = 0;
+        let mut maybe_applied = false;
+        loop {
+            attempts += 1;
+            let timeout = self
+                .attempt_timeout()
+                .min(deadline.saturating_duration_since(Instant::now()));
+            let res =
+                match req.try_clone().unwrap().timeout(timeout).send().await {
+                    Ok(res) => ValorantApiError::check(res).await,
+                    Err(err) => Err(err.into()),
+                };
+            let err = match res {
+                Ok(res) => return Ok(res),
+                Err(err @ ValorantApiError::Rejected { .. })
+                    if maybe_applied =>
+                {
+                    return Err(ValorantApiError::Replayed(Box::new(err)))
+                }
+                Err(err) => err,
+            };
+            maybe_applied |= err.maybe_applied();
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let Some(delay) = self.next_delay(&err, attempts, remaining) else {
+                return Err(err);
+            };
+            log::warn!(
+                "Attempt {attempts} failed, retrying in {}ms: {err}",
+                delay.as_millis()
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Conservative for most requests, aggressive for locking
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicies {
+    /// Lock and select (hover) requests
+    pub lock: RetryPolicy,
+    /// Everything else
+    pub default: RetryPolicy,
+}
+
+impl Default for RetryPolicies {
+    fn default() -> Self {
+        Self {
+            lock: RetryPolicy::aggressive(),
+            default: RetryPolicy::default(),
+        }
+    }
+}
+
+impl RetryPolicies {
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use reqwest::StatusCode;
+
+    use super::*;
+    use crate::valorant_client::error::RiotError;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn status(status: u16) -> ValorantApiError {
+        ValorantApiError::Status {
+            status: StatusCode::from_u16(status).unwrap(),
+            body: RiotError::default(),
+        }
+    }
+
+    #[test]
+    fn test_retryable_errors() {
+        let policy = RetryPolicy::default();
+        assert!(policy.next_delay(&status(503), 1, SECOND).is_some());
+        assert!(policy
+            .next_delay(&ValorantApiError::Timeout, 1, SECOND)
+            .is_some());
+        assert!(policy.next_delay(&status(501), 1, SECOND).is_none());
+        let conflict = ValorantApiError::Rejected {
+            status: StatusCode::CONFLICT,
+            body: RiotError::default(),
+        };
+        assert!(policy.next_delay(&conflict, 1, SECOND).is_none());
+        // out of attempts
+        assert!(policy.next_delay(&status(503), 3, SECOND).is_none());
+
+        let policy = RetryPolicy {
+            retry_statuses: vec![],
+            retry_connection_errors: false,
+            ..RetryPolicy::default()
+        };
+        assert!(policy.next_delay(&status(503), 1, SECOND).is_none());
+        assert!(policy
+            .next_delay(&ValorantApiError::Timeout, 1, SECOND)
+            .is_none());
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            backoff_ms: 100,
+            max_backoff_ms: 300,
+            jitter_ms: 0,
+            max_attempts: 10,
+            ..RetryPolicy::default()
+        };
+        let delays: Vec<_> = (1..5)
+            .map(|attempts| {
+                policy.next_delay(&status(503), attempts, SECOND).unwrap()
+            })
+            .collect();
+        assert_eq!(
+            delays,
+            [100, 200, 300, 300].map(Duration::from_millis).to_vec()
+        );
+        // the deadline would pass while waiting
+        assert!(policy
+            .next_delay(&status(503), 1, Duration::from_millis(50))
+            .is_none());
+
+        let jittered = RetryPolicy {
+            jitter_ms: 50,
+            ..policy.clone()
+        };
+        for _ in 0..20 {
+            let delay = jittered.next_delay(&status(503), 1, SECOND).unwrap();
+            assert!((100..=150).contains(&delay.as_millis()));
+        }
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let policy = RetryPolicy {
+            jitter_ms: 0,
+            ..RetryPolicy::default()
+        };
+        let rate_limited = |retry_after| ValorantApiError::RateLimited {
+            retry_after,
+            body: RiotError::default(),
+        };
+        assert_eq!(
+            policy.next_delay(&rate_limited(Some(SECOND)), 1, 2 * SECOND),
+            Some(SECOND)
+        );
+        assert_eq!(
+            policy.next_delay(&rate_limited(None), 1, 2 * SECOND),
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(
+            policy.next_delay(&rate_limited(Some(3 * SECOND)), 1, 2 * SECOND),
+            None
+        );
+    }
+
+    #[test]
+    fn test_config_defaults() {
+        let policies: RetryPolicies =
+            serde_json::from_str(r#"{"default": {"max_attempts": 1}}"#)
+                .unwrap();
+        assert_eq!(
+            policies.default,
+            RetryPolicy {
+                max_attempts: 1,
+                ..RetryPolicy::default()
+            }
+        );
+        assert_eq!(policies.lock, RetryPolicy::aggressive());
+        assert!(!policies.is_default());
+    }
+}
@@ -7,7 +7,7 @@ use tokio::sync::mpsc::{
 };
 use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message};
 
-use crate::lockfile::Lockfile;
+use crate::{endpoints::Endpoints, lockfile::Lockfile};
 
 use super::types::{
     ClientStatus, Event, EventKind, MessagingServiceMessage, ValorantClientAuth,
@@ -67,9 +67,12 @@ pub struct ValorantEventStream {
 }
 
 impl ValorantEventStream {
-    pub async fn connect(lockfile: &Lockfile) -> anyhow::Result<Self> {
+    pub async fn connect(
+        lockfile: &Lockfile,
+        endpoints: &Endpoints,
+    ) -> anyhow::Result<Self> {
         log::info!("Connecting ValorantEventStream");
-        let mut ws = connect_local_websocket(lockfile).await?;
+        let mut ws = connect_local_websocket(lockfile, endpoints).await?;
         log::debug!("Subscribing to Valorant events {:?}", EventKind::VARIANTS);
         subscribe_val_events(&mut ws).await?;
         let (tx, rx) = tokio::sync::mpsc::channel(100);
@@ -193,8 +196,11 @@ fn proxy_ws_events(tx: Sender<ValorantEvent>, mut ws: TokioWebsocketStream) {
 
 async fn connect_local_websocket(
     lockfile: &Lockfile,
+    endpoints: &Endpoints,
 ) -> anyhow::Result<TokioWebsocketStream> {
-    let mut request = lockfile.websocket_addr().into_client_request().unwrap();
+    let mut request = endpoints
+        .local_websocket(lockfile)
+        .into_client_request()?;
     request
         .headers_mut()
         .insert(http::header::AUTHORIZATION, lockfile.auth_header());
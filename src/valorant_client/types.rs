@@ -167,7 +167,7 @@ pub struct ClientStatus {
     // client_platform_info: ClientPlatformInfo,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum GameLoopState {
     Pregame,
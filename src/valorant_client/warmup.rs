@@ -0,0 +1,131 @@
+//! Keeps a pooled connection to the glz host open while in the menus, so the
+//! first lock request of a pregame skips DNS, TCP and TLS setup.
+//!
+//! The round trip over a new connection is measured once and compared to the
+//! pooled one, the difference is shown when instalocking.
+use std::{sync::atomic::Ordering, time::Duration};
+
+use reqwest::Client;
+use tokio::time::Instant;
+
+use super::{error::ApiResult, types::GameLoopState, ValorantClient};
+
+#[derive(Debug, Default)]
+pub struct ConnectionWarmth {
+    /// Round trip over a new connection
+    cold: Option<Duration>,
+    /// Last round trip over the pooled connection
+    warm: Option<Duration>,
+    warmed_at: Option<Instant>,
+}
+
+impl ConnectionWarmth {
+    /// Round trip time the pooled connection saves, `None` if it was not
+    /// used within `max_idle` and may be closed by now
+    fn saved(&self, max_idle: Duration) -> Option<Duration> {
+        if self.warmed_at?.elapsed() > max_idle {
+            return None;
+        }
+        Some(self.cold?.saturating_sub(self.warm?))
+            .filter(|saved| !saved.is_zero())
+    }
+}
+
+impl ValorantClient {
+    /// Pings the glz host every [`Config::glz_keep_warm_ms`] while in the
+    /// menus, until the client is dropped
+    ///
+    /// [`Config::glz_keep_warm_ms`]: crate::config::Config::glz_keep_warm_ms
+    pub fn spawn_keep_warm(&self) {
+        let this = self.clone();
+        tokio::spawn(async move {
+            let cold = Client::builder()
+                .danger_accept_invalid_certs(true)
+                .pool_max_idle_per_host(0)
+                .build()
+                .unwrap();
+            match this.ping_glz(&cold).await {
+                Ok(rtt) => this.warmth.lock().cold = Some(rtt),
+                Err(err) => log::warn!("Failed to reach glz: {err}"),
+            }
+            while this.running.load(Ordering::Relaxed) {
+                let interval = this.keep_warm_interval();
+                if interval.is_zero() {
+                    break;
+                }
+                if this.loop_state() == GameLoopState::Menus {
+                    this.keep_warm().await;
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    async fn keep_warm(&self) {
+        let res = self.ping_glz(&self.client).await;
+        let mut warmth = self.warmth.lock();
+        match res {
+            Ok(rtt) => {
+                if warmth.warm.is_none() {
+                    log::info!(
+                        "glz round trip: {}ms on a new connection, {}ms on a \
+                         pooled one",
+                        warmth.cold.unwrap_or_default().as_millis(),
+                        rtt.as_millis()
+                    );
+                }
+                warmth.warm = Some(rtt);
+                warmth.warmed_at = Some(Instant::now());
+            }
+            Err(err) => {
+                log::warn!("Failed to keep the glz connection warm: {err}");
+                warmth.warmed_at = None;
+            }
+        }
+    }
+
+    /// Any response will do, the request is only sent for the connection
+    async fn ping_glz(&self, client: &Client) -> ApiResult<Duration> {
+        let begin = Instant::now();
+        client
+            .head(self.glz(""))
+            .timeout(Duration::from_secs(5))
+            .send()
+            .await?;
+        Ok(begin.elapsed())
+    }
+
+    fn keep_warm_interval(&self) -> Duration {
+        Duration::from_millis(self.config.borrow().glz_keep_warm_ms)
+    }
+
+    /// Estimate of what the warm connection saves on the first request of a
+    /// pregame, for the status line
+    pub fn warm_connection_saved(&self) -> Option<Duration> {
+        // the pregame started at most one interval after the last ping
+        let max_idle = self.keep_warm_interval() * 2;
+        self.warmth.lock().saved(max_idle)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_saved() {
+        let mut warmth = ConnectionWarmth {
+            cold: Some(Duration::from_millis(120)),
+            warm: Some(Duration::from_millis(30)),
+            warmed_at: Some(Instant::now() - Duration::from_secs(2)),
+        };
+        assert_eq!(
+            warmth.saved(Duration::from_secs(5)),
+            Some(Duration::from_millis(90))
+        );
+        assert_eq!(warmth.saved(Duration::from_secs(1)), None);
+        warmth.warm = Some(Duration::from_millis(150));
+        assert_eq!(warmth.saved(Duration::from_secs(5)), None);
+        assert_eq!(ConnectionWarmth::default().saved(Duration::MAX), None);
+    }
+}
@@ -0,0 +1,387 @@
+//! Outgoing webhooks for instalocks and match start/end.
+//!
+//! Driven by the lifecycle events, so posting never delays the lock request
+//! itself. The targets are read from the active config for every event.
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::{sync::broadcast::error::RecvError, task::JoinHandle};
+
+use crate::{
+    config::AgentId,
+    events::{self, LifecycleEvent, TimedEvent},
+    valorant_client::{http::CharacterSelectionState, types::GameLoopState},
+    CONFIG,
+};
+
+const ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+#[derive(
+    Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub enum WebhookFormat {
+    /// The notification as JSON object, see [`Notification`]
+    #[default]
+    Json,
+    /// `{"content": "..."}` as expected by Discord webhooks
+    Discord,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Notification {
+    AgentLocked {
+        agent: AgentId,
+        agent_name: String,
+        latency_ms: u64,
+    },
+    AllAgentsFailed {
+        match_id: Option<String>,
+        attempts: usize,
+    },
+    MatchStarted {
+        match_id: Option<String>,
+    },
+    MatchEnded {
+        match_id: Option<String>,
+    },
+}
+
+impl Notification {
+    fn message(&self) -> String {
+        match self {
+            Self::AgentLocked {
+                agent_name,
+                latency_ms,
+                ..
+            } => format!("Instalocked **{agent_name}** after {latency_ms}ms"),
+            Self::AllAgentsFailed { attempts, .. } => {
+                format!("Failed to lock any of {attempts} agents")
+            }
+            Self::MatchStarted { .. } => "Match started".to_string(),
+            Self::MatchEnded { .. } => "Match ended".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JsonPayload<'a> {
+    time: String,
+    #[serde(flatten)]
+    notification: &'a Notification,
+}
+
+/// Turns lifecycle events into notifications, tracking the match across
+/// events since the menus event does not carry its id anymore
+#[derive(Debug, Default)]
+struct Notifier {
+    loop_state: Option<GameLoopState>,
+    match_id: Option<String>,
+}
+
+impl Notifier {
+    fn notification(&mut self, event: &LifecycleEvent) -> Option<Notification> {
+        match event {
+            LifecycleEvent::LockSucceeded {
+                agent,
+                agent_name,
+                state: CharacterSelectionState::Locked,
+                latency_ms,
+            } => Some(Notification::AgentLocked {
+                agent: agent.clone(),
+                agent_name: agent_name.clone(),
+                latency_ms: *latency_ms,
+            }),
+            LifecycleEvent::AllAgentsFailed { match_id, attempts } => {
+                Some(Notification::AllAgentsFailed {
+                    match_id: match_id.clone(),
+                    attempts: *attempts,
+                })
+            }
+            LifecycleEvent::LoopStateChanged {
+                loop_state,
+                match_id,
+            } => {
+                let prev = self.loop_state.replace(*loop_state);
+                let prev_match_id =
+                    std::mem::replace(&mut self.match_id, match_id.clone());
+                match (prev, loop_state) {
+                    (Some(GameLoopState::Ingame), GameLoopState::Ingame) => {
+                        None
+                    }
+                    (_, GameLoopState::Ingame) => {
+                        Some(Notification::MatchStarted {
+                            match_id: match_id.clone(),
+                        })
+                    }
+                    (Some(GameLoopState::Ingame), GameLoopState::Menus) => {
+                        Some(Notification::MatchEnded {
+                            match_id: prev_match_id,
+                        })
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Posts notifications to the configured webhooks until the event channel
+/// is closed
+pub fn spawn() -> JoinHandle<()> {
+    let mut rx = events::subscribe();
+    tokio::spawn(async move {
+        let client =
+            reqwest::Client::builder().timeout(TIMEOUT).build().unwrap();
+        let mut notifier = Notifier::default();
+        loop {
+            let TimedEvent { time, event } = match rx.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!("Webhooks skipped {skipped} events");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let Some(notification) = notifier.notification(&event) else {
+                continue;
+            };
+            let webhooks = CONFIG.borrow().webhooks.clone();
+            for webhook in webhooks {
+                let client = client.clone();
+                let notification = notification.clone();
+                tokio::spawn(async move {
+                    let time = time.to_rfc3339();
+                    if let Err(err) = deliver(
+                        &client,
+                        &webhook,
+                        &notification,
+                        &time,
+                        RETRY_DELAY,
+                    )
+                    .await
+                    {
+                        log::error!(
+                            "Failed to post webhook to {}: {err:#}",
+                            webhook.url
+                        );
+                    }
+                });
+            }
+        }
+    })
+}
+
+/// Retries server errors, rate limits and connection failures, waiting
+/// `retry_delay` times the attempt in between
+async fn deliver(
+    client: &reqwest::Client,
+    webhook: &WebhookConfig,
+    notification: &Notification,
+    time: &str,
+    retry_delay: Duration,
+) -> anyhow::Result<()> {
+    let body = match webhook.format {
+        WebhookFormat::Json => serde_json::to_value(JsonPayload {
+            time: time.to_string(),
+            notification,
+        })?,
+        WebhookFormat::Discord => {
+            serde_json::json!({ "content": notification.message() })
+        }
+    };
+    let mut attempt = 1;
+    loop {
+        let res = client.post(&webhook.url).json(&body).send().await;
+        let err = match res {
+            Ok(res) if res.status().is_success() => return Ok(()),
+            Ok(res) => {
+                let status = res.status();
+                let err = anyhow::anyhow!("Webhook returned {status}");
+                if !status.is_server_error()
+                    && status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                {
+                    return Err(err);
+                }
+                err
+            }
+            Err(err) => err.into(),
+        };
+        if attempt >= ATTEMPTS {
+            return Err(err);
+        }
+        log::warn!(
+            "Webhook attempt {attempt} to {} failed, retrying: {err:#}",
+            webhook.url
+        );
+        tokio::time::sleep(retry_delay * attempt).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+
+    use axum::{extract::State, http::StatusCode, routing::post, Json, Router};
+    use parking_lot::Mutex;
+    use serde_json::Value;
+
+    use super::*;
+
+    const RETRY: Duration = Duration::from_millis(1);
+
+    #[derive(Default)]
+    struct Sink {
+        failures: AtomicU32,
+        bodies: Mutex<Vec<Value>>,
+    }
+
+    /// Answers the first `failures` requests with 503
+    async fn start_sink(failures: u32) -> (String, Arc<Sink>) {
+        let sink = Arc::new(Sink {
+            failures: AtomicU32::new(failures),
+            ..Default::default()
+        });
+        let app =
+            Router::new()
+                .route(
+                    "/hook",
+                    post(
+                        |State(sink): State<Arc<Sink>>,
+                         Json(body): Json<Value>| async move {
+                            sink.bodies.lock().push(body);
+                            let failures = sink.failures.load(Ordering::SeqCst);
+                            if failures > 0 {
+                                sink.failures
+                                    .store(failures - 1, Ordering::SeqCst);
+                                return StatusCode::SERVICE_UNAVAILABLE;
+                            }
+                            StatusCode::NO_CONTENT
+                        },
+                    ),
+                )
+                .with_state(Arc::clone(&sink));
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (format!("http://{addr}/hook"), sink)
+    }
+
+    fn locked() -> Notification {
+        Notification::AgentLocked {
+            agent: "agent-id".to_string(),
+            agent_name: "Jett".to_string(),
+            latency_ms: 42,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_deliver_formats() {
+        let (url, sink) = start_sink(0).await;
+        let client = reqwest::Client::new();
+        let mut webhook = WebhookConfig {
+            url,
+            format: WebhookFormat::Json,
+        };
+        deliver(&client, &webhook, &locked(), "time", RETRY)
+            .await
+            .unwrap();
+        webhook.format = WebhookFormat::Discord;
+        deliver(&client, &webhook, &locked(), "time", RETRY)
+            .await
+            .unwrap();
+        let bodies = sink.bodies.lock().clone();
+        assert_eq!(
+            bodies,
+            vec![
+                serde_json::json!({
+                    "time": "time",
+                    "event": "agent_locked",
+                    "agent": "agent-id",
+                    "agent_name": "Jett",
+                    "latency_ms": 42,
+                }),
+                serde_json::json!({
+                    "content": "Instalocked **Jett** after 42ms"
+                }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deliver_retries() {
+        let (url, sink) = start_sink(2).await;
+        let client = reqwest::Client::new();
+        let webhook = WebhookConfig {
+            url,
+            format: WebhookFormat::Json,
+        };
+        deliver(&client, &webhook, &locked(), "time", RETRY)
+            .await
+            .unwrap();
+        assert_eq!(sink.bodies.lock().len(), 3);
+
+        sink.failures.store(ATTEMPTS, Ordering::SeqCst);
+        assert!(deliver(&client, &webhook, &locked(), "time", RETRY)
+            .await
+            .is_err());
+        assert_eq!(sink.bodies.lock().len(), 3 + ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn test_notifications() {
+        let mut notifier = Notifier::default();
+        let loop_state = |loop_state, match_id: Option<&str>| {
+            LifecycleEvent::LoopStateChanged {
+                loop_state,
+                match_id: match_id.map(str::to_string),
+            }
+        };
+        let lock = |state| LifecycleEvent::LockSucceeded {
+            agent: "agent-id".to_string(),
+            agent_name: "Jett".to_string(),
+            state,
+            latency_ms: 42,
+        };
+        let notifications: Vec<_> = [
+            loop_state(GameLoopState::Pregame, Some("m1")),
+            lock(CharacterSelectionState::Selected),
+            lock(CharacterSelectionState::Locked),
+            loop_state(GameLoopState::Ingame, Some("m1")),
+            loop_state(GameLoopState::Menus, None),
+            // dodged pregame
+            loop_state(GameLoopState::Pregame, Some("m2")),
+            loop_state(GameLoopState::Menus, None),
+        ]
+        .iter()
+        .filter_map(|event| notifier.notification(event))
+        .collect();
+        assert_eq!(
+            notifications,
+            vec![
+                locked(),
+                Notification::MatchStarted {
+                    match_id: Some("m1".to_string())
+                },
+                Notification::MatchEnded {
+                    match_id: Some("m1".to_string())
+                },
+            ]
+        );
+    }
+}